@@ -1,4 +1,5 @@
 #![warn(missing_docs)]
+#![cfg_attr(all(test, owning_ref_bench), feature(test))]
 
 /*!
 # An owning reference.
@@ -80,6 +81,8 @@ fn main() {
 # fn rc_ref() {}
 # #[cfg(feature = "nightly")]
 # fn rc_ref() {
+    use std::rc::Rc;
+
     let rc: RcRef<[i32]> = RcRef::new(Rc::new([1, 2, 3, 4]) as Rc<[i32]>);
     assert_eq!(&*rc, &[1, 2, 3, 4]);
 
@@ -109,6 +112,7 @@ fn main() {
 # #[cfg(feature = "nightly")]
 # fn arc_ref() {
     use std::thread;
+    use std::sync::Arc;
 
     fn par_sum(rc: ArcRef<[i32]>) -> i32 {
         if rc.len() == 0 {
@@ -136,12 +140,71 @@ fn main() {
 ```
 */
 
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "either")]
+extern crate either;
+#[cfg(feature = "memmap2")]
+extern crate memmap2;
+#[cfg(feature = "parking_lot")]
+extern crate parking_lot;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "spin")]
+extern crate spin;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
 /// Marker trait for expressing that the memory address of the value
 /// reachable via a dereference remains identical even if `self` gets moved.
+///
+/// # Safety
+///
+/// Implementors must guarantee that dereferencing `self` always yields the
+/// same address, for as long as `self` is alive, no matter where `self` is moved to.
+///
+/// # Example
+///
+/// Custom smart pointers from other crates can become owners by
+/// implementing this trait, and `CloneStableAddress` if clones share
+/// the same allocation:
+///
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{OwningRef, StableAddress, CloneStableAddress};
+/// use std::ops::Deref;
+/// use std::rc::Rc;
+///
+/// struct MyArc<T>(Rc<T>);
+///
+/// impl<T> Clone for MyArc<T> {
+///     fn clone(&self) -> MyArc<T> { MyArc(self.0.clone()) }
+/// }
+///
+/// impl<T> Deref for MyArc<T> {
+///     type Target = T;
+///     fn deref(&self) -> &T { &self.0 }
+/// }
+///
+/// unsafe impl<T> StableAddress for MyArc<T> {}
+/// unsafe impl<T> CloneStableAddress for MyArc<T> {}
+///
+/// fn main() {
+///     let or = OwningRef::new(MyArc(Rc::new((1, 2)))).map(|t| &t.1);
+///     assert_eq!(*or.clone(), 2);
+/// }
+/// ```
 pub unsafe trait StableAddress: Deref {}
 
 /// Marker trait for expressing that the memory address of the value
 /// reachable via a dereference remains identical even if `self` is a clone.
+///
+/// # Safety
+///
+/// Implementors must guarantee that a clone of `self` dereferences to the
+/// same address as `self`, and keeps it alive independently of `self`.
 pub unsafe trait CloneStableAddress: StableAddress + Clone {}
 
 /// An owning reference.
@@ -152,10 +215,88 @@ pub unsafe trait CloneStableAddress: StableAddress + Clone {}
 ///
 /// The owner is usually a pointer that points at some base type.
 ///
+/// An owning reference is `Send` and `Sync` only if its owner is, and only
+/// if a `&T` would be, so interior mutable referents can not be shared
+/// across threads:
+///
+/// ```compile_fail
+/// extern crate owning_ref;
+/// use owning_ref::ArcRef;
+/// use std::cell::Cell;
+/// use std::sync::Arc;
+///
+/// fn assert_sync<T: Sync>(_: &T) {}
+///
+/// fn main() {
+///     let or: ArcRef<Cell<i32>> = ArcRef::new(Arc::new(Cell::new(1)));
+///     assert_sync(&or);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// extern crate owning_ref;
+/// use owning_ref::BoxRef;
+/// use std::cell::Cell;
+///
+/// fn assert_send<T: Send>(_: &T) {}
+///
+/// fn main() {
+///     let or: BoxRef<(i32, Cell<i32>), Cell<i32>> =
+///         BoxRef::new(Box::new((1, Cell::new(2)))).map(|t| &t.1);
+///     assert_send(&or);
+/// }
+/// ```
+///
+/// Owning references with uniquely owned owners convert into ones with
+/// other owners with `From`: `BoxRef` into `RcRef` and `ArcRef`, and
+/// `VecRef` and `BoxRef` of slices into each other. Shared owners can not
+/// be converted back, since others might still point at their target;
+/// see `ArcRef::try_unwrap_to_box` for doing so only when that is not the case.
+///
+/// ```compile_fail
+/// extern crate owning_ref;
+/// use owning_ref::{BoxRef, RcRef};
+/// use std::rc::Rc;
+///
+/// fn main() {
+///     let or: BoxRef<i32> = RcRef::new(Rc::new(1)).into();
+/// }
+/// ```
+///
 /// For more details and examples, see the module and method docs.
 pub struct OwningRef<O, T: ?Sized> {
     owner: O,
     reference: *const T,
+    location: MapLocation,
+}
+
+/// A mutable owning reference.
+///
+/// This wraps an owner `O` and a reference `&mut T` pointing
+/// at something reachable from `O::Target` while keeping
+/// the ability to move `self` around.
+///
+/// The owner is usually a pointer that points at some base type.
+///
+/// For more details and examples, see the module and method docs.
+pub struct OwningRefMut<O, T: ?Sized> {
+    owner: O,
+    reference: *mut T,
+}
+
+/// An owning handle.
+///
+/// This wraps an owner `O` and a handle `H` created from a pointer to
+/// `O::Target`, for example a lock guard borrowing from a lock the owner
+/// points at. The handle is dropped before the owner, and dereferencing
+/// an owning handle dereferences the handle.
+///
+/// For more details and examples, see the module and method docs.
+pub struct OwningHandle<O, H>
+    where O: StableAddress, H: Deref,
+{
+    handle: H,
+    _owner: O,
 }
 
 /// Helper trait for an erased concrete type an owner dereferences to.
@@ -165,15 +306,92 @@ pub trait Erased {}
 impl<T> Erased for T {}
 
 /// Helper trait for erasing the concrete type of what an owner derferences to,
-/// for example `Box<T> -> Box<Erased>`. This would be unneeded with
+/// for example `Box<T> -> Box<dyn Erased>`. This would be unneeded with
 /// higher kinded types support in the language.
-pub unsafe trait IntoErased {
+///
+/// # Safety
+///
+/// Implementors must guarantee that the erased owner keeps everything
+/// reachable from the original owner alive at the same address.
+pub unsafe trait IntoErased<'a> {
     /// Owner with the dereference type substituted to `Erased`.
     type Erased;
     /// Perform the type erasure.
     fn into_erased(self) -> Self::Erased;
 }
 
+/// Helper trait for owners that can be unwrapped down to the innermost,
+/// real owner, as done by `OwningRef::flatten_all`.
+///
+/// This is implemented for owning references, recursing into their owner,
+/// and for the std owner types, ending the recursion.
+///
+/// # Safety
+///
+/// Implementors must guarantee that the root owner keeps everything
+/// reachable from the original owner alive at the same address.
+pub unsafe trait IntoRootOwner {
+    /// The innermost owner.
+    type Root;
+    /// Discards all layers around the innermost owner.
+    fn into_root_owner(self) -> Self::Root;
+}
+
+/// An `Erased` trait object that can be sent and shared between threads.
+pub type SyncErased = dyn Erased + Send + Sync;
+
+/// Helper trait like `IntoErased`, for owners whose erased
+/// base type stays thread safe, for example `Box<T> -> Box<SyncErased>`.
+///
+/// # Safety
+///
+/// Implementors must guarantee that the erased owner keeps everything
+/// reachable from the original owner alive at the same address.
+pub unsafe trait IntoSyncErased<'a> {
+    /// Owner with the dereference type substituted to `SyncErased`.
+    type Erased;
+    /// Perform the type erasure.
+    fn into_sync_erased(self) -> Self::Erased;
+}
+
+/// Helper trait for referents that can be projected into by an index,
+/// as done by `OwningRef::project_owned`.
+///
+/// This is implemented for slices, vectors and hash maps,
+/// and can be implemented for containers of other crates as well.
+/// Implementations that panic should be marked `#[track_caller]`,
+/// so that `project_owned` reports panics at its caller.
+pub trait Project<Idx> {
+    /// The type of what is reachable by an index.
+    type Output: ?Sized;
+    /// Returns a reference to what is reachable by `idx`,
+    /// panicking if there is nothing.
+    fn project(&self, idx: Idx) -> &Self::Output;
+    /// Returns a reference to what is reachable by `idx`,
+    /// or `None` if there is nothing.
+    ///
+    /// The default implementation calls `project`, so implementations
+    /// that can fail should override it.
+    fn try_project(&self, idx: Idx) -> Option<&Self::Output> {
+        Some(self.project(idx))
+    }
+}
+
+/// Helper trait for referent types whose pointers can be moved to another
+/// address, as done when the referent is moved along with its owner,
+/// for example by `OwningRef::arc_to_rc`.
+///
+/// This is implemented for sized types, slices and `str`.
+///
+/// # Safety
+///
+/// Implementors must return a pointer to `addr`, with the provenance of
+/// `addr` and the metadata of `reference`.
+pub unsafe trait Relocate {
+    /// Returns a pointer to `addr` describing a value like `reference` does.
+    fn relocate(reference: *const Self, addr: *const u8) -> *const Self;
+}
+
 /////////////////////////////////////////////////////////////////////////////
 // inherent API
 /////////////////////////////////////////////////////////////////////////////
@@ -199,14 +417,73 @@ impl<O, T: ?Sized> OwningRef<O, T> {
         OwningRef {
             reference: &*o,
             owner: o,
+            location: MapLocation::NONE,
         }
     }
 
+    /// Creates a new owning reference like `new`,
+    /// after initializing the owner's target in place with `init`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRef;
+    ///
+    /// fn main() {
+    ///     let owning_ref = OwningRef::new_init(Box::new([0; 3]), |a| a[1] = 5);
+    ///     assert_eq!(*owning_ref, [0, 5, 0]);
+    /// }
+    /// ```
+    pub fn new_init<F>(mut o: O, init: F) -> Self
+        where O: StableAddress,
+              O: DerefMut<Target = T>,
+              F: FnOnce(&mut T)
+    {
+        init(&mut *o);
+        OwningRef::new(o)
+    }
+
+    /// Creates a new owning reference like `new`, and in debug builds
+    /// asserts that the owner's target did not move along with the owner.
+    ///
+    /// This is meant for testing custom `StableAddress` implementations.
+    /// Release builds skip the check and behave exactly like `new`, since a
+    /// single move can only catch some broken owners, and should not cost
+    /// anything where the implementation is already trusted.
+    ///
+    /// # Panics
+    ///
+    /// Panics in debug builds if the owner does not uphold `StableAddress`
+    /// for this move.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRef;
+    ///
+    /// fn main() {
+    ///     let owning_ref = OwningRef::new_checked(Box::new(42));
+    ///     assert_eq!(*owning_ref, 42);
+    /// }
+    /// ```
+    #[cfg_attr(debug_assertions, track_caller)]
+    pub fn new_checked(o: O) -> Self
+        where O: StableAddress,
+              O: Deref<Target = T>,
+    {
+        let or = OwningRef::new(o);
+        debug_assert!(ptr::eq(or.reference, &*or.owner),
+                      "the target of the owner moved along with it");
+        or
+    }
+
     /// Converts `self` into a new owning reference that points at something reachable
     /// from the previous one.
     ///
     /// This can be a reference to a field of `U`, something reachable from a field of
     /// `U`, or even something unrelated with a `'static` lifetime.
+    /// The latter includes constant literals like `&[1, 2, 3]`,
+    /// which are promoted to `'static` memory.
     ///
     /// # Example
     /// ```
@@ -222,6 +499,33 @@ impl<O, T: ?Sized> OwningRef<O, T> {
     ///     assert_eq!(*owning_ref, 3);
     /// }
     /// ```
+    ///
+    /// The closure can only return references to something that outlives
+    /// its argument, not to values computed by it:
+    ///
+    /// ```compile_fail,E0515
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new(42));
+    ///     let or = or.map(|x| &x.to_string());
+    /// }
+    /// ```
+    ///
+    /// Use `map_boxed` to keep a computed value alongside the owner instead:
+    ///
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new(42));
+    ///     let or = or.map_boxed(|x| Box::new(x.to_string()));
+    ///     assert_eq!(&*or, "42");
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
     pub fn map<F, U: ?Sized>(self, f: F) -> OwningRef<O, U>
         where O: StableAddress,
               F: FnOnce(&T) -> &U
@@ -229,296 +533,6921 @@ impl<O, T: ?Sized> OwningRef<O, T> {
         OwningRef {
             reference: f(&self),
             owner: self.owner,
+            location: MapLocation::caller(),
         }
     }
 
-    /// Erases the concrete base type of the owner with a trait object.
+    /// Same as `map`, spelled out for building owning references from
+    /// blocking or async code.
     ///
-    /// This allows mixing of owned references with different owner base types.
+    /// The projection always runs synchronously, so it can not await
+    /// anything. Async code should produce the owner first, then build the
+    /// owning reference inside something like `spawn_blocking` and send it
+    /// back, which works whenever the owner and referent are `Send`.
     ///
     /// # Example
     /// ```
     /// extern crate owning_ref;
-    /// use owning_ref::{OwningRef, Erased};
+    /// use owning_ref::BoxRef;
+    /// use std::thread;
     ///
     /// fn main() {
-    ///     // NB: Using the concrete types here for explicitnes.
-    ///     // For less verbose code type aliases like `BoxRef` are provided.
-    ///
-    ///     let owning_ref_a: OwningRef<Box<[i32; 4]>, [i32; 4]>
-    ///         = OwningRef::new(Box::new([1, 2, 3, 4]));
-    ///
-    ///     let owning_ref_b: OwningRef<Box<Vec<(i32, bool)>>, Vec<(i32, bool)>>
-    ///         = OwningRef::new(Box::new(vec![(0, false), (1, true)]));
+    ///     let or = thread::spawn(|| {
+    ///         BoxRef::new(Box::new([1, 2, 3])).map_blocking(|a| &a[1])
+    ///     }).join().unwrap();
     ///
-    ///     let owning_ref_a: OwningRef<Box<[i32; 4]>, i32>
-    ///         = owning_ref_a.map(|a| &a[0]);
+    ///     assert_eq!(*or, 2);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_blocking<F, U: ?Sized>(self, f: F) -> OwningRef<O, U>
+        where O: StableAddress,
+              F: FnOnce(&T) -> &U
+    {
+        self.map(f)
+    }
+
+    /// Same as `map`, named for projecting to a trait object.
     ///
-    ///     let owning_ref_b: OwningRef<Box<Vec<(i32, bool)>>, i32>
-    ///         = owning_ref_b.map(|a| &a[1].0);
+    /// Naming the trait object type up front lets the closure return
+    /// a plain reference and have it unsized, without an `as &dyn Trait` cast.
     ///
-    ///     let owning_refs: [OwningRef<Box<Erased>, i32>; 2]
-    ///         = [owning_ref_a.erase_owner(), owning_ref_b.erase_owner()];
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    /// use std::fmt::Display;
     ///
-    ///     assert_eq!(*owning_refs[0], 1);
-    ///     assert_eq!(*owning_refs[1], 1);
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new((42, "answer")));
+    ///     let or = or.map_dyn::<dyn Display, _>(|t| &t.0);
+    ///     assert_eq!(or.to_string(), "42");
     /// }
     /// ```
-    pub fn erase_owner(self) -> OwningRef<O::Erased, T>
-        where O: IntoErased,
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_dyn<Tr: ?Sized, F>(self, f: F) -> OwningRef<O, Tr>
+        where O: StableAddress,
+              F: FnOnce(&T) -> &Tr
     {
-        OwningRef {
-            reference: self.reference,
-            owner: self.owner.into_erased(),
-        }
+        self.map(f)
     }
 
-    // TODO: wrap_owner
-
-    // FIXME: Naming convention?
-    /// A getter for the underlying owner.
-    pub fn owner(&self) -> &O {
-        &self.owner
+    /// Same as `map`, but also returns a pointer to what the owner
+    /// dereferences to, for tracking the identity of the owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3]));
+    ///     let base = or.owner_ptr();
+    ///
+    ///     let (or, ptr) = or.map_with_owner_ptr(|a| &a[2]);
+    ///     assert_eq!(*or, 3);
+    ///     assert_eq!(ptr, base);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_with_owner_ptr<F, U: ?Sized>(self, f: F) -> (OwningRef<O, U>, *const O::Target)
+        where O: StableAddress,
+              F: FnOnce(&T) -> &U
+    {
+        let or = self.map(f);
+        let ptr = or.owner_ptr();
+        (or, ptr)
     }
 
-    // FIXME: Naming convention?
-    /// Discards the reference and retrieves the owner.
-    pub fn into_inner(self) -> O {
-        self.owner
+    /// Same as `map`, but `f` also computes an owned token from the referent,
+    /// which is returned alongside the new owning reference.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("hello world".to_string());
+    ///     let (or, len) = or.map_with_token(|s| {
+    ///         let word = &s[6..];
+    ///         (word, word.len())
+    ///     });
+    ///
+    ///     assert_eq!(&*or, "world");
+    ///     assert_eq!(len, 5);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_with_token<U: ?Sized, Tok, F>(self, f: F) -> (OwningRef<O, U>, Tok)
+        where O: StableAddress,
+              F: FnOnce(&T) -> (&U, Tok)
+    {
+        let (reference, token) = f(&self);
+        let reference: *const U = reference;
+        let or = OwningRef {
+            owner: self.owner,
+            reference,
+            location: MapLocation::caller(),
+        };
+        (or, token)
     }
-}
-
-/////////////////////////////////////////////////////////////////////////////
-// std traits
-/////////////////////////////////////////////////////////////////////////////
-
-use std::ops::Deref;
-use std::convert::From;
-use std::fmt::{self, Debug};
-use std::marker::{Send, Sync};
-
-impl<O, T: ?Sized> Deref for OwningRef<O, T> {
-    type Target = T;
 
-    fn deref(&self) -> &T {
-        unsafe {
-            &*self.reference
+    /// Converts `self` into a new owning reference to a box created by `f`
+    /// from the current referent, keeping both the box and the previous
+    /// owner alive.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3]));
+    ///     let or = or.map_boxed(|a| Box::new(a.iter().sum::<i32>()));
+    ///     assert_eq!(*or, 6);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_boxed<U: ?Sized, F>(self, f: F) -> OwningRef<(O, Box<U>), U>
+        where F: FnOnce(&T) -> Box<U>
+    {
+        let value = f(&self);
+        OwningRef {
+            reference: &*value,
+            owner: (self.owner, value),
+            location: MapLocation::caller(),
         }
     }
-}
-
-impl<O, T: ?Sized> From<O> for OwningRef<O, T>
-    where O: StableAddress, O: Deref<Target = T>,
-{
-    fn from(owner: O) -> Self {
-        OwningRef::new(owner)
-    }
-}
-
-// ^ FIXME: Is a Into impl for calling into_inner() possible as well?
-
-impl<O, T: ?Sized> Debug for OwningRef<O, T>
-    where O: Debug, T: Debug,
-{
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "OwningRef {{ owner: {:?}, reference: {:?} }}",
-               self.owner(), &**self)
-    }
-}
 
-impl<O, T: ?Sized> Clone for OwningRef<O, T>
-    where O: CloneStableAddress,
-{
-    fn clone(&self) -> Self {
+    /// Converts `self` into a new owning reference to the values `f` pushes
+    /// into a fresh vector, keeping both the vector and the previous owner alive.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("a,b,c".to_string());
+    ///     let or = or.map_into_vec(|s, v| v.extend(s.split(',').map(|p| p.to_uppercase())));
+    ///     assert_eq!(&*or, &["A", "B", "C"]);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_into_vec<U, F>(self, f: F) -> OwningRef<(O, Box<Vec<U>>), [U]>
+        where F: FnOnce(&T, &mut Vec<U>)
+    {
+        let mut values = Box::default();
+        f(&self, &mut values);
         OwningRef {
-            owner: self.owner.clone(),
-            reference: self.reference,
+            reference: &values[..],
+            owner: (self.owner, values),
+            location: MapLocation::caller(),
         }
     }
-}
-
-unsafe impl<O: Send, T: ?Sized> Send for OwningRef<O, T> {}
-unsafe impl<O: Sync, T: ?Sized> Sync for OwningRef<O, T> {}
 
-impl Debug for Erased {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        write!(f, "<Erased>",)
+    /// Converts `self` into a new owning reference to the value in the
+    /// `OnceCell` that `cell` provides, filling it from the referent with
+    /// `init` unless it is already set. The cell is kept alive with the previous owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    /// use std::cell::OnceCell;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("hello".to_string());
+    ///     let or = or.map_lazy(OnceCell::new, |s| s.len());
+    ///     assert_eq!(*or, 5);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_lazy<U, F, G>(self, cell: G, init: F) -> OwningRef<(O, Box<OnceCell<U>>), U>
+        where G: FnOnce() -> OnceCell<U>,
+              F: FnOnce(&T) -> U
+    {
+        let cell = Box::new(cell());
+        let reference: *const U = cell.get_or_init(|| init(&self));
+        OwningRef {
+            reference,
+            owner: (self.owner, cell),
+            location: MapLocation::caller(),
+        }
     }
-}
 
+    /// Builds an index of positions in the referent with `build`, and
+    /// converts `self` into a new owning reference to what `pick` selects
+    /// with its help. The index is kept alive with the previous owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("a,b,c".to_string());
+    ///     let or = or.map_with_index(
+    ///         |s| s.match_indices(',').enumerate().map(|(i, (p, _))| (i, p)).collect(),
+    ///         |s, index| &s[index[&0] + 1..index[&1]],
+    ///     );
+    ///
+    ///     assert_eq!(&*or, "b");
+    ///     assert_eq!(or.owner().1.len(), 2);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_with_index<K, F, G, U: ?Sized>(self, build: F, pick: G)
+        -> OwningRef<(O, Box<HashMap<K, usize>>), U>
+        where F: FnOnce(&T) -> HashMap<K, usize>,
+              G: for<'a> FnOnce(&'a T, &'a HashMap<K, usize>) -> &'a U
+    {
+        let index = Box::new(build(&self));
+        let reference: *const U = pick(&self, &index);
+        OwningRef {
+            reference,
+            owner: (self.owner, index),
+            location: MapLocation::caller(),
+        }
+    }
+
+    /// Moves the reference in place to something else of the same type
+    /// reachable from the owner, like a cursor over the owner's target.
+    ///
+    /// `f` is given both the owner's target and the current referent.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let mut or = BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[0]);
+    ///     or.reproject(|a, _| &a[2]);
+    ///     assert_eq!(*or, 3);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn reproject<F>(&mut self, f: F)
+        where O: StableAddress,
+              F: for<'a> FnOnce(&'a O::Target, &'a T) -> &'a T
+    {
+        let reference: *const T = f(&self.owner, self);
+        self.reference = reference;
+        self.location = MapLocation::caller();
+    }
+
+    /// Converts `self` into a new owning reference, exactly like `map`.
+    ///
+    /// This exists to spell out a part of the contract of `map`: since the owner
+    /// keeps its target alive at a stable address, `f` may return a reference
+    /// to anything reachable from it, up to and including the target itself.
+    /// Returning the target resets the owning reference to what `new`
+    /// would have created from the owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3, 4])).map(|a| &a[..]);
+    ///     let or = or.map_or_owner_target(|s| &s[3]);
+    ///     assert_eq!(*or, 4);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_or_owner_target<F, U: ?Sized>(self, f: F) -> OwningRef<O, U>
+        where O: StableAddress,
+              F: FnOnce(&T) -> &U
+    {
+        self.map(f)
+    }
+
+    /// Converts `self` into a new owning reference that points
+    /// at what is reachable from the referent by `idx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx` does not lead anywhere, for example
+    /// if it is out of bounds of a slice.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::VecRef;
+    ///
+    /// fn main() {
+    ///     let or = VecRef::new(vec![1, 2, 3, 4]).project_owned(2);
+    ///     assert_eq!(*or, 3);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn project_owned<Idx>(self, idx: Idx) -> OwningRef<O, T::Output>
+        where O: StableAddress,
+              T: Project<Idx>,
+    {
+        // Projecting outside of a closure reports panics at the caller.
+        let reference: *const T::Output = (*self).project(idx);
+        OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        }
+    }
+
+    /// Like `project_owned`, but discards the reference and returns
+    /// the owner instead of panicking if `idx` does not lead anywhere.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::VecRef;
+    ///
+    /// fn main() {
+    ///     let owner = VecRef::new(vec![1, 2]).checked_project_owned(2).unwrap_err();
+    ///     assert_eq!(owner, [1, 2]);
+    /// }
+    /// ```
+    pub fn checked_project_owned<Idx>(self, idx: Idx) -> Result<OwningRef<O, T::Output>, O>
+        where O: StableAddress,
+              T: Project<Idx>,
+    {
+        let reference: *const T::Output = match (*self).try_project(idx) {
+            Some(reference) => reference,
+            None => return Err(self.owner),
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+
+    /// Clones the referent into an owned value, independent of the owner.
+    ///
+    /// This allows dropping a large owner while keeping a small part of it.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new("hello world".to_string())).map(|s| &s[6..]);
+    ///     let world: String = or.detach();
+    ///     drop(or);
+    ///
+    ///     assert_eq!(world, "world");
+    /// }
+    /// ```
+    pub fn detach(&self) -> T::Owned
+        where T: ToOwned,
+    {
+        (**self).to_owned()
+    }
+
+    /// Returns a view of the bytes of the plain old data referent.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    /// # #[cfg(feature = "bytemuck")]
+    /// # {
+    ///     let or = BoxRef::new(Box::new((1u8, [2u8, 3]))).map(|t| &t.1);
+    ///     assert_eq!(or.as_raw_bytes(), [2, 3]);
+    /// # }
+    /// }
+    /// ```
+    #[cfg(feature = "bytemuck")]
+    pub fn as_raw_bytes(&self) -> &[u8]
+        where T: bytemuck::Pod,
+    {
+        bytemuck::bytes_of(&**self)
+    }
+
+    /// Converts `self` into a new owning reference if `f` borrows from the referent,
+    /// or into the value `f` computed otherwise, dropping the owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, MapCow};
+    /// use std::borrow::Cow;
+    ///
+    /// fn trimmed(s: &str) -> Cow<str> {
+    ///     match s.strip_suffix('!') {
+    ///         Some(s) => Cow::Borrowed(s),
+    ///         None => Cow::Owned(s.to_uppercase()),
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new("hi!".to_string())).map(|s| &s[..]);
+    ///     let cow = or.map_cow(trimmed);
+    ///     assert_eq!(&*cow, "hi");
+    ///     assert!(match cow { MapCow::Borrowed(_) => true, MapCow::Owned(_) => false });
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_cow<F>(self, f: F) -> MapCow<O, T>
+        where O: StableAddress,
+              T: ToOwned,
+              F: FnOnce(&T) -> Cow<T>
+    {
+        match f(&self) {
+            Cow::Borrowed(reference) => {
+                let reference: *const T = reference;
+                MapCow::Borrowed(OwningRef {
+                    owner: self.owner,
+                    reference,
+                    location: MapLocation::caller(),
+                })
+            }
+            Cow::Owned(owned) => MapCow::Owned(owned),
+        }
+    }
+
+    /// Creates an owning reference to what an `Arc` reachable from the referent
+    /// points at, sharing ownership of it and dropping the original owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{ArcRef, BoxRef};
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new((1, Arc::new(2))));
+    ///     let inner: ArcRef<i32> = or.reroot_arc(|t| &t.1);
+    ///
+    ///     assert_eq!(*inner, 2);
+    /// }
+    /// ```
+    pub fn reroot_arc<Inner: ?Sized, F>(self, f: F) -> ArcRef<Inner>
+        where Arc<Inner>: StableAddress<Target = Inner>,
+              F: FnOnce(&T) -> &Arc<Inner>
+    {
+        OwningRef::new(f(&self).clone())
+    }
+
+    /// Returns `self` unchanged, requiring the owner and referent to be `'static`.
+    ///
+    /// This does nothing at runtime, but spells out the bound required
+    /// for storing `self` in a `'static` context right where it is needed,
+    /// which gives errors closer to the cause than the ones of generic code.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    /// use std::any::Any;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[1]).require_static();
+    ///     let stored: Box<dyn Any> = Box::new(or);
+    ///
+    ///     assert!(stored.downcast_ref::<BoxRef<[i32; 3], i32>>().is_some());
+    /// }
+    /// ```
+    pub fn require_static(self) -> Self
+        where O: 'static,
+              T: 'static,
+    {
+        self
+    }
+
+    /// Discards the reference, mutates the owner's target with `f` and creates
+    /// a new owning reference to the whole target, returning it with the result of `f`.
+    ///
+    /// The old projection can not be kept, since the mutation may have
+    /// moved what it pointed at, so it has to be redone with `map`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new(vec![1, 2])).map(|v| &v[1]);
+    ///     let (or, len) = or.modify(|v| { v.push(3); v.len() });
+    ///
+    ///     assert_eq!(len, 3);
+    ///     assert_eq!(*or.map(|v| &v[2]), 3);
+    /// }
+    /// ```
+    pub fn modify<R, F>(self, f: F) -> (OwningRef<O, O::Target>, R)
+        where O: StableAddress + DerefMut,
+              F: FnOnce(&mut O::Target) -> R
+    {
+        let mut owner = self.owner;
+        let result = f(&mut owner);
+        (OwningRef::new(owner), result)
+    }
+
+    /// Erases the concrete base type of the owner with a trait object.
+    ///
+    /// This allows mixing of owned references with different owner base types.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{OwningRef, Erased};
+    ///
+    /// fn main() {
+    ///     // NB: Using the concrete types here for explicitnes.
+    ///     // For less verbose code type aliases like `BoxRef` are provided.
+    ///
+    ///     let owning_ref_a: OwningRef<Box<[i32; 4]>, [i32; 4]>
+    ///         = OwningRef::new(Box::new([1, 2, 3, 4]));
+    ///
+    ///     let owning_ref_b: OwningRef<Box<Vec<(i32, bool)>>, Vec<(i32, bool)>>
+    ///         = OwningRef::new(Box::new(vec![(0, false), (1, true)]));
+    ///
+    ///     let owning_ref_a: OwningRef<Box<[i32; 4]>, i32>
+    ///         = owning_ref_a.map(|a| &a[0]);
+    ///
+    ///     let owning_ref_b: OwningRef<Box<Vec<(i32, bool)>>, i32>
+    ///         = owning_ref_b.map(|a| &a[1].0);
+    ///
+    ///     let owning_refs: [OwningRef<Box<dyn Erased>, i32>; 2]
+    ///         = [owning_ref_a.erase_owner(), owning_ref_b.erase_owner()];
+    ///
+    ///     assert_eq!(*owning_refs[0], 1);
+    ///     assert_eq!(*owning_refs[1], 1);
+    /// }
+    /// ```
+    pub fn erase_owner<'a>(self) -> OwningRef<O::Erased, T>
+        where O: IntoErased<'a>,
+    {
+        OwningRef {
+            reference: self.reference,
+            owner: self.owner.into_erased(),
+            location: self.location,
+        }
+    }
+
+    /// Collapses owning references nested as owners of each other
+    /// into a single one, keeping only the innermost owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{OwningRef, BoxRef};
+    ///
+    /// fn main() {
+    ///     let inner = BoxRef::new(Box::new([[1, 2], [3, 4]])).map(|a| &a[1]);
+    ///     let outer = OwningRef::new(inner).map(|a| &a[0]);
+    ///
+    ///     let flat: BoxRef<[[i32; 2]; 2], i32> = outer.flatten_all();
+    ///     assert_eq!(*flat, 3);
+    /// }
+    /// ```
+    pub fn flatten_all(self) -> OwningRef<O::Root, T>
+        where O: IntoRootOwner,
+    {
+        OwningRef {
+            reference: self.reference,
+            owner: self.owner.into_root_owner(),
+            location: self.location,
+        }
+    }
+
+    /// Erases the concrete base type of the owner like `erase_owner`,
+    /// while keeping the owning reference `Send` and `Sync`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, SyncErasedBoxRef};
+    /// use std::thread;
+    ///
+    /// fn main() {
+    ///     let or: SyncErasedBoxRef<i32> = BoxRef::new(Box::new([1, 2]))
+    ///         .map(|a| &a[1])
+    ///         .erase_sync_owner();
+    ///
+    ///     assert_eq!(thread::spawn(move || *or).join().unwrap(), 2);
+    /// }
+    /// ```
+    pub fn erase_sync_owner<'a>(self) -> OwningRef<O::Erased, T>
+        where O: IntoSyncErased<'a>,
+    {
+        OwningRef {
+            reference: self.reference,
+            owner: self.owner.into_sync_erased(),
+            location: self.location,
+        }
+    }
+
+    /// Converts the owner with `f`, which also returns a pointer
+    /// to the referent of the new owning reference.
+    ///
+    /// If `f` fails, it has to hand the original owner back together
+    /// with its error, which is then returned as is.
+    ///
+    /// # Safety
+    ///
+    /// The pointer returned by `f` must stay valid for as long as the new
+    /// owner is alive, regardless of where it is moved to. In practice this
+    /// means it has to point at something reachable from `O2::Target`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new("hello".to_string()));
+    ///
+    ///     let or: BoxRef<str> = unsafe {
+    ///         or.try_map_owner(|owner| -> Result<_, ((), _)> {
+    ///             let owner = owner.into_boxed_str();
+    ///             let reference = &*owner as *const str;
+    ///             Ok((owner, reference))
+    ///         })
+    ///     }.ok().unwrap();
+    ///
+    ///     assert_eq!(&*or, "hello");
+    /// }
+    /// ```
+    pub unsafe fn try_map_owner<O2, U: ?Sized, F, E>(self, f: F)
+        -> Result<OwningRef<O2, U>, (E, O)>
+        where O2: StableAddress,
+              F: FnOnce(O) -> Result<(O2, *const U), (E, O)>
+    {
+        let (owner, reference) = f(self.owner)?;
+        Ok(OwningRef {
+            owner,
+            reference,
+            location: self.location,
+        })
+    }
+
+    // TODO: wrap_owner
+
+    // FIXME: Naming convention?
+    /// A getter for the underlying owner.
+    pub fn owner(&self) -> &O {
+        &self.owner
+    }
+
+    /// Returns where `map` or one of its variants taking a closure was last
+    /// called to create this owning reference, or what it was converted from.
+    ///
+    /// Locations are only recorded in debug builds, and are `None` in release
+    /// builds, or if `map` was never called.
+    #[cfg(feature = "debug-locations")]
+    pub fn last_map_location(&self) -> Option<&'static Location<'static>> {
+        self.location.get()
+    }
+
+    /// Calls `f` with a borrow of the underlying owner,
+    /// limiting the borrow to the duration of the call.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or = ArcRef::new(Arc::new([1, 2])).map(|a| &a[0]);
+    ///     assert_eq!(or.with_owner(Arc::strong_count), 1);
+    /// }
+    /// ```
+    pub fn with_owner<F, R>(&self, f: F) -> R
+        where F: FnOnce(&O) -> R
+    {
+        f(&self.owner)
+    }
+
+    /// Returns a pointer to what the owner dereferences to.
+    ///
+    /// This is not cached, as dereferencing a stable address
+    /// owner usually amounts to reading a single pointer.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let rc = Rc::new([1, 2, 3]);
+    ///     let or = RcRef::new(rc.clone()).map(|a| &a[1]);
+    ///
+    ///     assert_eq!(or.owner_ptr(), &*rc as *const [i32; 3]);
+    /// }
+    /// ```
+    pub fn owner_ptr(&self) -> *const O::Target
+        where O: StableAddress,
+    {
+        &*self.owner
+    }
+
+    /// Returns whether both owning references point at the same referent.
+    ///
+    /// Like `ptr::eq`, this compares the metadata of references
+    /// to unsized types as well.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{OwningRef, RcRef};
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let or = RcRef::new(Rc::new([1, 1])).map(|a| &a[0]);
+    ///     let other = or.clone().map(|_| &1);
+    ///
+    ///     assert!(OwningRef::ptr_eq(&or, &or.clone()));
+    ///     assert!(!OwningRef::ptr_eq(&or, &other));
+    /// }
+    /// ```
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        ptr::eq(this.reference, other.reference)
+    }
+
+    /// Returns the referent without going through `Deref`.
+    ///
+    /// This is an associated function so that it never shadows a method
+    /// of the referent, and can always be reached as `OwningRef::get(&or)`,
+    /// even from generic code.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{OwningRef, VecRef};
+    ///
+    /// fn main() {
+    ///     let or = VecRef::new(vec![1, 2, 3]);
+    ///
+    ///     assert_eq!(or.get(1), Some(&2));
+    ///     assert_eq!(OwningRef::get(&or), &[1, 2, 3]);
+    /// }
+    /// ```
+    pub fn get(this: &Self) -> &T {
+        unsafe { &*this.reference }
+    }
+
+    // FIXME: Naming convention?
+    /// Discards the reference and retrieves the owner.
+    pub fn into_inner(self) -> O {
+        self.owner
+    }
+}
+
+impl<O> OwningRef<O, str> {
+    /// Returns a `Debug` representation of the referenced string
+    /// that shows at most `max` characters, ending in `...` when it is longer.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("hello world".to_string());
+    ///     assert_eq!(format!("{:?}", or.debug_truncated(5)), "\"hello\"...");
+    /// }
+    /// ```
+    pub fn debug_truncated(&self, max: usize) -> impl Debug + '_ {
+        Truncated { value: &**self, max }
+    }
+
+    /// Returns `self` if the referenced string is not empty,
+    /// or discards the reference and returns the owner otherwise.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::StringRef;
+    ///
+    /// fn main() {
+    ///     let or = StringRef::new("hello".to_string()).map(|s| &s[5..]);
+    ///     assert_eq!(or.non_empty().unwrap_err(), "hello");
+    /// }
+    /// ```
+    pub fn non_empty(self) -> Result<Self, O> {
+        if self.is_empty() {
+            Err(self.owner)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Splits the referenced string into its lines,
+    /// each of them an owning reference sharing the owner of `self`.
+    ///
+    /// Lines are split the same way as by `str::lines`: they end with
+    /// either `\n` or `\r\n`, and a final empty line is not yielded.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let text = RcRef::new(Rc::new("foo\r\nbar\n".to_string())).map(|s| &s[..]);
+    ///     let lines: Vec<RcRef<String, str>> = text.lines_owned().collect();
+    ///
+    ///     assert_eq!(&*lines[0], "foo");
+    ///     assert_eq!(&*lines[1], "bar");
+    ///     assert_eq!(lines.len(), 2);
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn lines_owned(self) -> impl Iterator<Item = OwningRef<O, str>>
+        where O: CloneStableAddress,
+    {
+        let location = MapLocation::caller();
+        let mut start = 0;
+        iter::from_fn(move || {
+            if start == self.len() {
+                return None;
+            }
+            let (mut end, next) = match self[start..].find('\n') {
+                Some(i) => (start + i, start + i + 1),
+                None => (self.len(), self.len()),
+            };
+            if end < next && self[start..end].ends_with('\r') {
+                end -= 1;
+            }
+            let reference: *const str = &self[start..end];
+            start = next;
+            Some(OwningRef {
+                owner: self.owner.clone(),
+                reference,
+                location,
+            })
+        })
+    }
+}
+
+impl<O, A, B> OwningRef<O, Result<A, B>> {
+    /// Projects to the `Ok` value of the referenced result,
+    /// or discards the reference and returns the owner if it is an `Err`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or: BoxRef<Result<u32, ()>> = BoxRef::new(Box::new(Ok(5)));
+    ///     assert_eq!(*or.try_map_ok().unwrap(), 5);
+    /// }
+    /// ```
+    pub fn try_map_ok(self) -> Result<OwningRef<O, A>, O> {
+        let reference: *const A = match *self {
+            Ok(ref a) => a,
+            Err(_) => return Err(self.owner),
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+
+    /// Projects to the `Err` value of the referenced result,
+    /// or discards the reference and returns the owner if it is an `Ok`.
+    pub fn try_map_err(self) -> Result<OwningRef<O, B>, O> {
+        let reference: *const B = match *self {
+            Ok(_) => return Err(self.owner),
+            Err(ref b) => b,
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+}
+
+impl<O, T> OwningRef<O, Option<T>> {
+    /// Projects to the value inside the referenced option,
+    /// or discards the reference and returns the owner if it is `None`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new((Some(1), None::<i32>)));
+    ///     let some = or.map(|t| &t.0).flatten_option().unwrap();
+    ///     assert_eq!(*some, 1);
+    /// }
+    /// ```
+    pub fn flatten_option(self) -> Result<OwningRef<O, T>, O> {
+        let reference: *const T = match *self {
+            Some(ref t) => t,
+            None => return Err(self.owner),
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+}
+
+impl<O> OwningRef<O, dyn Any> {
+    /// Projects to the concrete type of the referenced `dyn Any`,
+    /// or returns `self` unchanged if it is not a `U`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRef;
+    /// use std::any::Any;
+    ///
+    /// fn main() {
+    ///     let or = OwningRef::new(Box::new(5u32)).map(|x| x as &dyn Any);
+    ///     assert_eq!(*or.downcast_ref_owned::<u32>().unwrap(), 5);
+    /// }
+    /// ```
+    pub fn downcast_ref_owned<U: 'static>(self) -> Result<OwningRef<O, U>, Self> {
+        let reference: *const U = match self.downcast_ref::<U>() {
+            Some(u) => u,
+            None => return Err(self),
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+}
+
+impl<U: ?Sized> OwningRef<Box<dyn Any>, U> {
+    /// Recovers the concrete type of an owner erased with `erase_owner_any`,
+    /// or returns `self` unchanged if it is not a `Box<T>`.
+    ///
+    /// The boxed value stays where it is, so the projection is kept as is
+    /// and can be continued from.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[1..]).erase_owner_any();
+    ///     let or: BoxRef<[i32; 3], [i32]> = or.downcast_owner().unwrap();
+    ///
+    ///     assert_eq!(*or.map(|s| &s[1]), 3);
+    /// }
+    /// ```
+    pub fn downcast_owner<T: Any>(self) -> Result<OwningRef<Box<T>, U>, Self> {
+        match self.owner.downcast() {
+            Ok(owner) => Ok(OwningRef {
+                reference: self.reference,
+                owner,
+                location: self.location,
+            }),
+            Err(owner) => Err(OwningRef {
+                reference: self.reference,
+                owner,
+                location: self.location,
+            }),
+        }
+    }
+}
+
+impl<O, K, V, S> OwningRef<O, HashMap<K, V, S>> {
+    /// Iterates over the entries of the referenced map, yielding owning references
+    /// to each key and value that share the owner of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::collections::HashMap;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let mut map = HashMap::new();
+    ///     map.insert("answer", 42);
+    ///
+    ///     let or = RcRef::new(Rc::new(map));
+    ///     let (key, value) = or.iter_owned().next().unwrap();
+    ///
+    ///     assert_eq!(*key, "answer");
+    ///     assert_eq!(*value, 42);
+    /// }
+    /// ```
+    pub fn iter_owned(self)
+        -> impl ExactSizeIterator<Item = (OwningRef<O, K>, OwningRef<O, V>)>
+        where O: CloneStableAddress,
+    {
+        let entries: Vec<(*const K, *const V)> = self.iter()
+            .map(|(k, v)| (k as *const K, v as *const V))
+            .collect();
+        entries.into_iter().map(move |(k, v)| {
+            let key = OwningRef { owner: self.owner.clone(), reference: k, location: self.location };
+            let value = OwningRef { owner: self.owner.clone(), reference: v, location: self.location };
+            (key, value)
+        })
+    }
+}
+
+impl<O, V, S> OwningRef<O, HashSet<V, S>>
+    where V: Eq + Hash,
+          S: BuildHasher,
+{
+    /// Looks up `value` in the referenced set, returning an owning reference
+    /// to the element equal to it that shares the owner of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::collections::HashSet;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let set: HashSet<String> = vec!["a".to_string(), "b".to_string()].into_iter().collect();
+    ///     let or = RcRef::new(Rc::new(set));
+    ///
+    ///     assert_eq!(&*or.get_owned("b").unwrap(), "b");
+    ///     assert!(or.get_owned("c").is_none());
+    /// }
+    /// ```
+    pub fn get_owned<Q>(&self, value: &Q) -> Option<OwningRef<O, V>>
+        where O: CloneStableAddress,
+              V: Borrow<Q>,
+              Q: ?Sized + Eq + Hash,
+    {
+        let reference: *const V = self.get(value)?;
+        Some(OwningRef {
+            owner: self.owner.clone(),
+            reference,
+            location: self.location,
+        })
+    }
+}
+
+impl<T> OwningRef<Vec<T>, [T]> {
+    /// Converts the owner into a boxed slice while keeping the projection.
+    ///
+    /// This uses `Vec::into_boxed_slice`, which reallocates the elements
+    /// if the vector has spare capacity. In that case a projection into the
+    /// vector is moved along with them, pointing at the same indices
+    /// of the new allocation afterwards.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::VecRef;
+    ///
+    /// fn main() {
+    ///     let mut v = Vec::with_capacity(8);
+    ///     v.extend_from_slice(&[1, 2, 3, 4]);
+    ///
+    ///     let or = VecRef::new(v).map(|v| &v[1..3]);
+    ///     let or = or.vec_ref_into_boxed();
+    ///     assert_eq!(&*or, &[2, 3]);
+    /// }
+    /// ```
+    pub fn vec_ref_into_boxed(self) -> OwningRef<Box<[T]>, [T]> {
+        let range = subslice_range(&self.owner, &self);
+        let owner = self.owner.into_boxed_slice();
+        let reference = match range {
+            Some(range) => &owner[range] as *const [T],
+            None => self.reference,
+        };
+        OwningRef {
+            reference,
+            owner,
+            location: self.location,
+        }
+    }
+}
+
+impl<T> OwningRef<Box<[T]>, [T]> {
+    /// Converts the owner into a vector while keeping the projection.
+    ///
+    /// This uses `<[T]>::into_vec`, which never reallocates,
+    /// so the projection stays untouched.
+    pub fn boxed_ref_into_vec(self) -> OwningRef<Vec<T>, [T]> {
+        OwningRef {
+            reference: self.reference,
+            owner: self.owner.into_vec(),
+            location: self.location,
+        }
+    }
+}
+
+/// The two halves of a split slice, sharing an owner.
+type SplitRefs<O, T> = (OwningRef<O, [T]>, OwningRef<O, [T]>);
+
+impl<O, T> OwningRef<O, [T]> {
+    /// Returns `self` if the referenced slice is not empty,
+    /// or discards the reference and returns the owner otherwise.
+    pub fn non_empty(self) -> Result<Self, O> {
+        if self.is_empty() {
+            Err(self.owner)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Returns a `Debug` representation of the referenced slice
+    /// that shows at most `max` elements, ending in `..` when it is longer.
+    pub fn debug_truncated(&self, max: usize) -> impl Debug + '_
+        where T: Debug,
+    {
+        Truncated { value: &**self, max }
+    }
+
+    /// Iterates over the elements of the referenced slice, yielding owning
+    /// references to each of them that share the owner of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let or = RcRef::new(Rc::new([1, 2, 3])).map(|a| &a[..]);
+    ///     let mut iter = or.iter_owned();
+    ///
+    ///     assert_eq!(*iter.next().unwrap(), 1);
+    ///     assert_eq!(*iter.next_back().unwrap(), 3);
+    ///     assert_eq!(*iter.next().unwrap(), 2);
+    ///     assert!(iter.next().is_none());
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn iter_owned(self) -> impl DoubleEndedIterator<Item = OwningRef<O, T>> + ExactSizeIterator
+        where O: CloneStableAddress,
+    {
+        let location = MapLocation::caller();
+        (0..self.len()).map(move |i| OwningRef {
+            reference: &self[i],
+            owner: self.owner.clone(),
+            location,
+        })
+    }
+
+    /// Splits the referenced slice into its first `len` elements and the rest,
+    /// as two owning references sharing the owner of `self`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is greater than the length of the slice.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let or = RcRef::new(Rc::new([1, 2, 3])).map(|a| &a[..]);
+    ///     let (head, tail) = or.map_split_prefix(1);
+    ///
+    ///     assert_eq!(&*head, &[1]);
+    ///     assert_eq!(&*tail, &[2, 3]);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn map_split_prefix(self, len: usize) -> (OwningRef<O, [T]>, OwningRef<O, [T]>)
+        where O: CloneStableAddress,
+    {
+        let (head, tail): (*const [T], *const [T]) = {
+            let (head, tail) = self.split_at(len);
+            (head, tail)
+        };
+        let head = OwningRef {
+            owner: self.owner.clone(),
+            reference: head,
+            location: self.location,
+        };
+        let tail = OwningRef {
+            owner: self.owner,
+            reference: tail,
+            location: self.location,
+        };
+        (head, tail)
+    }
+
+    /// Narrows the referenced slice down to the elements in `range`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds of the slice.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::VecRef;
+    ///
+    /// fn main() {
+    ///     let or = VecRef::new(vec![1, 2, 3, 4, 5]);
+    ///     assert_eq!(&*or.range(1..3), &[2, 3]);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn range(self, range: Range<usize>) -> OwningRef<O, [T]> {
+        let reference: *const [T] = &self[range];
+        OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        }
+    }
+
+    /// Like `range`, but discards the reference and returns
+    /// the owner instead of panicking if `range` is out of bounds.
+    pub fn checked_range(self, range: Range<usize>) -> Result<OwningRef<O, [T]>, O> {
+        let reference: *const [T] = match self.get(range) {
+            Some(reference) => reference,
+            None => return Err(self.owner),
+        };
+        Ok(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+
+    /// Converts the referenced slice into a reference to an array,
+    /// or returns `self` unchanged if its length is not `N`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::VecRef;
+    ///
+    /// fn main() {
+    ///     let or = VecRef::new(vec![1, 2, 3]);
+    ///     let or = or.try_into_array::<2>().unwrap_err();
+    ///     assert_eq!(*or.try_into_array::<3>().unwrap(), [1, 2, 3]);
+    /// }
+    /// ```
+    pub fn try_into_array<const N: usize>(self) -> Result<OwningRef<O, [T; N]>, Self> {
+        if self.len() != N {
+            return Err(self);
+        }
+        Ok(OwningRef {
+            reference: self.reference as *const [T; N],
+            owner: self.owner,
+            location: self.location,
+        })
+    }
+
+    /// Like `map_split_prefix`, but discards the reference and returns
+    /// the owner instead of panicking if `len` is out of bounds.
+    pub fn checked_split_prefix(self, len: usize) -> Result<SplitRefs<O, T>, O>
+        where O: CloneStableAddress,
+    {
+        if len > self.len() {
+            return Err(self.owner);
+        }
+        Ok(self.map_split_prefix(len))
+    }
+
+    /// Projects each element of the referenced slice with `f` in parallel,
+    /// creating owning references sharing the owner of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    /// # #[cfg(feature = "rayon")]
+    /// # {
+    ///     let or = ArcRef::new(Arc::new([(1, 'a'), (2, 'b')])).map(|a| &a[..]);
+    ///     let numbers = or.par_map_each(|t| &t.0);
+    ///
+    ///     assert_eq!(*numbers[1], 2);
+    /// # }
+    /// }
+    /// ```
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn par_map_each<F, U: ?Sized + Sync>(self, f: F) -> Vec<OwningRef<O, U>>
+        where O: CloneStableAddress + Send + Sync,
+              T: Sync,
+              F: Fn(&T) -> &U + Sync
+    {
+        use rayon::prelude::*;
+
+        let location = MapLocation::caller();
+        let or = &self;
+        let f = &f;
+        (0..self.len()).into_par_iter()
+            .map(|i| {
+                let reference: *const U = f(&or[i]);
+                OwningRef {
+                    owner: or.owner.clone(),
+                    reference,
+                    location,
+                }
+            })
+            .collect()
+    }
+
+    /// Like `iter_owned`, but starting at the back of the referenced slice.
+    pub fn iter_owned_rev(self) -> impl DoubleEndedIterator<Item = OwningRef<O, T>> + ExactSizeIterator
+        where O: CloneStableAddress,
+    {
+        self.iter_owned().rev()
+    }
+
+    /// Trims the referenced slice to start at its first element
+    /// whose address is aligned to `N` bytes.
+    ///
+    /// This keeps the same part of the slice as the middle and suffix
+    /// returned by `slice::align_to`. Returns `None` if no such element exists.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is not a power of two.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([0u8; 64])).map(|a| &a[3..]);
+    ///     let or = or.map_aligned::<8>().unwrap();
+    ///
+    ///     assert_eq!(or.as_ptr() as usize % 8, 0);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn map_aligned<const N: usize>(self) -> Option<OwningRef<O, [T]>>
+        where O: StableAddress,
+    {
+        assert!(N.is_power_of_two(), "alignment is not a power of two");
+        let offset = self.as_ptr().align_offset(N);
+        if offset >= self.len() {
+            return None;
+        }
+        Some(self.map(|s| &s[offset..]))
+    }
+
+    /// Returns whether the referenced slice shares any bytes with the one
+    /// referenced by `other`.
+    ///
+    /// Empty slices never overlap anything.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let or = RcRef::new(Rc::new([1, 2, 3, 4])).map(|a| &a[..]);
+    ///     let head = or.clone().map(|s| &s[..2]);
+    ///     let tail = or.clone().map(|s| &s[2..]);
+    ///
+    ///     assert!(!head.overlaps(&tail));
+    ///     assert!(or.overlaps(&tail));
+    /// }
+    /// ```
+    pub fn overlaps<O2>(&self, other: &OwningRef<O2, [T]>) -> bool {
+        let start = self.as_ptr() as usize;
+        let end = start + mem::size_of_val::<[T]>(self);
+        let other_start = other.as_ptr() as usize;
+        let other_end = other_start + mem::size_of_val::<[T]>(other);
+        start < end && other_start < other_end && start < other_end && other_start < end
+    }
+}
+
+impl<O> OwningRef<O, [u8]> {
+    /// Decodes a big-endian `u32` from the first bytes of the referenced slice,
+    /// bundling the decoded value with the owner.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the slice is shorter than the integer.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([0, 0, 1, 2, 3])).map(|a| &a[..]);
+    ///     let or = or.read_u32_be();
+    ///
+    ///     assert_eq!(*or, 0x0102);
+    ///     assert_eq!(or.owner().0[4], 3);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn read_u32_be(self) -> OwningRef<(O, Box<u32>), u32> {
+        self.read_int(u32::from_be_bytes)
+    }
+
+    /// Decodes a big-endian `u16` like `read_u32_be` does a big-endian `u32`.
+    #[track_caller]
+    pub fn read_u16_be(self) -> OwningRef<(O, Box<u16>), u16> {
+        self.read_int(u16::from_be_bytes)
+    }
+
+    /// Decodes a little-endian `u16` like `read_u32_be` does a big-endian `u32`.
+    #[track_caller]
+    pub fn read_u16_le(self) -> OwningRef<(O, Box<u16>), u16> {
+        self.read_int(u16::from_le_bytes)
+    }
+
+    /// Decodes a little-endian `u32` like `read_u32_be` does a big-endian `u32`.
+    #[track_caller]
+    pub fn read_u32_le(self) -> OwningRef<(O, Box<u32>), u32> {
+        self.read_int(u32::from_le_bytes)
+    }
+
+    /// Decodes a big-endian `u64` like `read_u32_be` does a big-endian `u32`.
+    #[track_caller]
+    pub fn read_u64_be(self) -> OwningRef<(O, Box<u64>), u64> {
+        self.read_int(u64::from_be_bytes)
+    }
+
+    /// Decodes a little-endian `u64` like `read_u32_be` does a big-endian `u32`.
+    #[track_caller]
+    pub fn read_u64_le(self) -> OwningRef<(O, Box<u64>), u64> {
+        self.read_int(u64::from_le_bytes)
+    }
+
+    /// Converts `self` into an owning reference to the longest prefix
+    /// of the referenced bytes that is valid UTF-8.
+    ///
+    /// This cuts off a codepoint truncated at the end of a buffer,
+    /// as well as everything from the first invalid sequence on.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new(*b"caf\xc3")).map(|a| &a[..]);
+    ///     assert_eq!(&*or.valid_utf8_prefix(), "caf");
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn valid_utf8_prefix(self) -> OwningRef<O, str>
+        where O: StableAddress,
+    {
+        self.map(|bytes| {
+            let len = match str::from_utf8(bytes) {
+                Ok(s) => s.len(),
+                Err(e) => e.valid_up_to(),
+            };
+            unsafe { str::from_utf8_unchecked(&bytes[..len]) }
+        })
+    }
+
+    /// Converts `self` into an owning reference to a plain old data value
+    /// viewing the bytes of the referenced slice.
+    ///
+    /// Returns `None` if the slice is not aligned for `U`,
+    /// or does not have exactly the size of `U`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// # #[cfg(feature = "bytemuck")]
+    /// extern crate bytemuck;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    /// # #[cfg(feature = "bytemuck")]
+    /// # {
+    ///     let or = BoxRef::new(Box::new([7u16, 0]));
+    ///     let bytes = or.map(|a| bytemuck::cast_slice::<u16, u8>(a));
+    ///
+    ///     assert_eq!(*bytes.map_pod::<[u16; 2]>().unwrap(), [7, 0]);
+    /// # }
+    /// }
+    /// ```
+    #[cfg(feature = "bytemuck")]
+    pub fn map_pod<U: bytemuck::Pod>(self) -> Option<OwningRef<O, U>> {
+        let reference: *const U = bytemuck::try_from_bytes(&self).ok()?;
+        Some(OwningRef {
+            owner: self.owner,
+            reference,
+            location: self.location,
+        })
+    }
+
+    #[track_caller]
+    fn read_int<const N: usize, U>(self, decode: fn([u8; N]) -> U) -> OwningRef<(O, Box<U>), U> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self[..N]);
+        bundle(self.owner, decode(bytes))
+    }
+}
+
+impl<T, U: ?Sized> OwningRef<Box<T>, U> {
+    /// Erases the concrete type of the owner like `erase_owner`,
+    /// but into a `Box<dyn Any>` that can be downcast back with `downcast_owner`.
+    ///
+    /// The boxed value stays where it is, so the projection is kept as is.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, OwningRef};
+    /// use std::any::Any;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[1..]);
+    ///     let refs: Vec<OwningRef<Box<dyn Any>, [i32]>> = vec![or.erase_owner_any()];
+    ///
+    ///     assert_eq!(&*refs[0], &[2, 3]);
+    /// }
+    /// ```
+    pub fn erase_owner_any(self) -> OwningRef<Box<dyn Any>, U>
+        where T: 'static,
+    {
+        OwningRef {
+            reference: self.reference,
+            owner: self.owner,
+            location: self.location,
+        }
+    }
+
+    /// Moves the boxed value into an `Rc`, making the owning reference cheaply clonable.
+    ///
+    /// A projection into the boxed value is moved along with it. Projections
+    /// to anything else, like the heap buffer of a boxed `String`, stay untouched.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, RcRef};
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new((1, 2))).map(|t| &t.1);
+    ///     let or: RcRef<(i32, i32), i32> = or.into_shared();
+    ///
+    ///     assert_eq!(*or.clone(), 2);
+    /// }
+    /// ```
+    pub fn into_shared(self) -> OwningRef<Rc<T>, U>
+        where U: Relocate,
+    {
+        let offset = offset_in(&*self, &*self.owner);
+        let owner = Rc::new(*self.owner);
+        OwningRef {
+            reference: unsafe { move_reference(self.reference, offset, &*owner) },
+            owner,
+            location: self.location,
+        }
+    }
+
+    /// Moves the box into an `Arc`, calling `f` once the last
+    /// owning reference sharing it has been dropped.
+    ///
+    /// The boxed value stays where it is, so the projection is kept as is.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    /// use std::thread;
+    ///
+    /// fn main() {
+    ///     let expired = Arc::new(AtomicBool::new(false));
+    ///     let flag = expired.clone();
+    ///
+    ///     let or = BoxRef::new(Box::new([1, 2])).map(|a| &a[1])
+    ///         .on_expire(move || flag.store(true, Ordering::SeqCst));
+    ///     let clone = or.clone();
+    ///
+    ///     drop(or);
+    ///     assert!(!expired.load(Ordering::SeqCst));
+    ///     thread::spawn(move || drop(clone)).join().unwrap();
+    ///     assert!(expired.load(Ordering::SeqCst));
+    /// }
+    /// ```
+    pub fn on_expire<F>(self, f: F) -> OwningRef<Arc<OnDrop<Box<T>>>, U>
+        where F: FnOnce() + Send + 'static
+    {
+        OwningRef {
+            reference: self.reference,
+            owner: Arc::new(OnDrop::new(self.owner, f)),
+            location: self.location,
+        }
+    }
+}
+
+impl<'a, T: ?Sized, U: ?Sized> OwningRef<&'a T, U> {
+    /// Shortens the lifetime of the borrowed owner.
+    ///
+    /// This is what covariance does implicitly, spelled out
+    /// for places where it does not kick in on its own.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRef;
+    ///
+    /// fn main() {
+    ///     static DATA: [i32; 3] = [1, 2, 3];
+    ///     let or: OwningRef<&'static [i32; 3], i32> = OwningRef::new(&DATA).map(|a| &a[1]);
+    ///
+    ///     let local = 2;
+    ///     let refs: Vec<OwningRef<&[i32; 3], i32>> = vec![or.shorten_owner()];
+    ///     assert_eq!(*refs[0], local);
+    /// }
+    /// ```
+    pub fn shorten_owner<'b>(self) -> OwningRef<&'b T, U>
+        where 'a: 'b,
+    {
+        OwningRef {
+            owner: self.owner,
+            reference: self.reference,
+            location: self.location,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> OwningRef<&'a T, T> {
+    /// Creates a new owning reference that borrows `r` instead of owning it.
+    ///
+    /// The result can not outlive the borrowed value:
+    ///
+    /// ```compile_fail,E0597
+    /// extern crate owning_ref;
+    /// use owning_ref::RefOwningRef;
+    ///
+    /// fn main() {
+    ///     let or: RefOwningRef<(i32, i32), i32>;
+    ///     {
+    ///         let local = (1, 2);
+    ///         or = RefOwningRef::from_ref(&local).map(|t| &t.1);
+    ///     }
+    ///     assert_eq!(*or, 2);
+    /// }
+    /// ```
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RefOwningRef;
+    ///
+    /// fn main() {
+    ///     let local = (1, 2);
+    ///     let or: RefOwningRef<(i32, i32), i32> = RefOwningRef::from_ref(&local).map(|t| &t.1);
+    ///     assert_eq!(*or, 2);
+    /// }
+    /// ```
+    pub fn from_ref(r: &'a T) -> Self {
+        OwningRef::new(r)
+    }
+}
+
+impl<O, T: ?Sized> OwningRefMut<O, T> {
+    /// Creates a new mutable owning reference from a owner
+    /// initialized to the direct mutable dereference of it.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRefMut;
+    ///
+    /// fn main() {
+    ///     let mut owning_ref_mut = OwningRefMut::new(Box::new(42));
+    ///     *owning_ref_mut += 1;
+    ///     assert_eq!(*owning_ref_mut, 43);
+    /// }
+    /// ```
+    pub fn new(mut o: O) -> Self
+        where O: StableAddress,
+              O: DerefMut<Target = T>,
+    {
+        OwningRefMut {
+            reference: &mut *o,
+            owner: o,
+        }
+    }
+
+    /// Converts `self` into a new mutable owning reference that points at
+    /// something reachable from the previous one.
+    ///
+    /// This can be a reference to a field of `U`, something reachable from a field of
+    /// `U`, or even something unrelated with a `'static` lifetime.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRefMut;
+    ///
+    /// fn main() {
+    ///     let owning_ref_mut = OwningRefMut::new(Box::new([1, 2, 3, 4]));
+    ///
+    ///     // create a mutable owning reference that points at the
+    ///     // third element of the array.
+    ///     let mut owning_ref_mut = owning_ref_mut.map_mut(|array| &mut array[2]);
+    ///     *owning_ref_mut = 42;
+    ///     assert_eq!(*owning_ref_mut, 42);
+    /// }
+    /// ```
+    pub fn map_mut<F, U: ?Sized>(mut self, f: F) -> OwningRefMut<O, U>
+        where O: StableAddress,
+              F: FnOnce(&mut T) -> &mut U
+    {
+        OwningRefMut {
+            reference: f(&mut self),
+            owner: self.owner,
+        }
+    }
+
+    /// Creates a shared owning reference to the same referent,
+    /// borrowing the owner for as long as it is used.
+    ///
+    /// The mutable owning reference can not be used until the shared
+    /// one is dropped, after which it can be mutated through again.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRefMut;
+    ///
+    /// fn main() {
+    ///     let mut or = OwningRefMut::new(Box::new([1, 2, 3])).map_mut(|a| &mut a[1]);
+    ///
+    ///     let shared = or.reborrow_shared();
+    ///     let copy = shared.clone();
+    ///     assert_eq!(*shared + *copy, 4);
+    ///     drop((shared, copy));
+    ///
+    ///     *or = 5;
+    ///     assert_eq!(*or, 5);
+    /// }
+    /// ```
+    pub fn reborrow_shared(&self) -> OwningRef<&O, T> {
+        OwningRef {
+            owner: &self.owner,
+            reference: self.reference,
+            location: MapLocation::NONE,
+        }
+    }
+
+    // FIXME: Naming convention?
+    /// A getter for the underlying owner.
+    pub fn owner(&self) -> &O {
+        &self.owner
+    }
+
+    // FIXME: Naming convention?
+    /// Discards the reference and retrieves the owner.
+    pub fn into_inner(self) -> O {
+        self.owner
+    }
+}
+
+impl<O, T> OwningRefMut<O, [T]> {
+    /// Calls `f` on disjoint chunks of `n` elements of the referenced slice
+    /// in parallel, one scoped thread per chunk, collecting the results in order.
+    ///
+    /// The last chunk is shorter if the length of the slice is not a multiple of `n`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero, or if `f` panics.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRefMut;
+    ///
+    /// fn main() {
+    ///     let mut or = OwningRefMut::new(vec![1, 2, 3, 4, 5]);
+    ///     let sums = or.par_chunks_mut(2, |c| c.iter().sum::<i32>());
+    ///
+    ///     assert_eq!(sums, [3, 7, 5]);
+    /// }
+    /// ```
+    pub fn par_chunks_mut<R, F>(&mut self, n: usize, f: F) -> Vec<R>
+        where T: Send,
+              R: Send,
+              F: Fn(&mut [T]) -> R + Sync
+    {
+        let f = &f;
+        thread::scope(|s| {
+            let workers: Vec<_> = self.chunks_mut(n)
+                .map(|chunk| s.spawn(move || f(chunk)))
+                .collect();
+            workers.into_iter()
+                .map(|w| w.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+}
+
+impl<T: ?Sized> OwningRefMut<Pin<Box<T>>, T> {
+    /// Creates a new pinned mutable owning reference from a pinned box,
+    /// pointing at whatever `f` pin-projects the boxed value to.
+    ///
+    /// A field is structurally pinned exactly when `f` projects to it
+    /// through `Pin<&mut T>`, with `Pin::map_unchecked_mut` or a pin-projection
+    /// helper, and the pinning rules for such fields apply as usual.
+    /// Fields `f` does not reach are not pinned by this.
+    ///
+    /// The result derefs to `U`, but since it is wrapped in a `Pin`
+    /// it only hands out `Pin<&mut U>` unless `U: Unpin`,
+    /// and the referent stays put for as long as the box lives.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningRefMut;
+    /// use std::marker::PhantomPinned;
+    /// use std::pin::Pin;
+    ///
+    /// struct Pinned {
+    ///     id: u32,
+    ///     _pin: PhantomPinned,
+    /// }
+    ///
+    /// struct Outer {
+    ///     inner: Pinned,
+    /// }
+    ///
+    /// fn main() {
+    ///     let boxed = Box::pin(Outer { inner: Pinned { id: 7, _pin: PhantomPinned } });
+    ///     // `inner` is structurally pinned.
+    ///     let mut or = OwningRefMut::project_pin(boxed, |o| unsafe {
+    ///         o.map_unchecked_mut(|o| &mut o.inner)
+    ///     });
+    ///
+    ///     let pinned: Pin<&mut Pinned> = or.as_mut();
+    ///     assert_eq!(pinned.id, 7);
+    /// }
+    /// ```
+    pub fn project_pin<U: ?Sized, F>(mut owner: Pin<Box<T>>, f: F) -> Pin<OwningRefMut<Pin<Box<T>>, U>>
+        where F: FnOnce(Pin<&mut T>) -> Pin<&mut U>
+    {
+        let reference: *mut U = unsafe { Pin::get_unchecked_mut(f(owner.as_mut())) };
+        // The referent lives in the box, which keeps it in place when the
+        // owning reference moves, and it is only reachable through the `Pin`.
+        unsafe {
+            Pin::new_unchecked(OwningRefMut {
+                owner,
+                reference,
+            })
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<T: Clone, U: ?Sized> OwningRef<Arc<[T]>, U> {
+    /// Copies the shared slice into an `Rc`, for when the data
+    /// is not used by multiple threads anymore.
+    ///
+    /// A projection into the slice is moved along with it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the referent does not lie within the slice, for example
+    /// in the heap buffer of a `String` element, since the copy would
+    /// still point into the original that is dropped here.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{ArcRef, RcRef};
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or: ArcRef<[i32]> = ArcRef::new(Arc::from(vec![1, 2, 3]));
+    ///     let or: RcRef<[i32], [i32]> = or.map(|s| &s[1..]).arc_to_rc();
+    ///
+    ///     assert_eq!(&*or, &[2, 3]);
+    /// }
+    /// ```
+    ///
+    /// The resulting owning reference can not be sent to other threads anymore:
+    ///
+    /// ```compile_fail
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn assert_send<T: Send>(_: T) {}
+    ///
+    /// fn main() {
+    ///     let or: ArcRef<[i32]> = ArcRef::new(Arc::from(vec![1, 2, 3]));
+    ///     assert_send(or.arc_to_rc());
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn arc_to_rc(self) -> OwningRef<Rc<[T]>, U>
+        where U: Relocate,
+    {
+        let offset = offset_in(&*self, &*self.owner);
+        assert!(offset.is_some(), "referent does not lie within the shared slice");
+        let owner: Rc<[T]> = Rc::from(&*self.owner);
+        OwningRef {
+            reference: unsafe { move_reference(self.reference, offset, &*owner) },
+            owner,
+            location: self.location,
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<T: Clone> OwningRef<Arc<[T]>, [T]> {
+    /// Sets the element at `index` of the referenced slice to `value`,
+    /// first cloning the shared slice if other `Arc`s point at it.
+    ///
+    /// The result refers to the same part of the slice as `self`, and other
+    /// owning references sharing the original slice are unaffected. If the
+    /// referenced slice is not part of the owner's, it is cloned into a new `Arc`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds of the referenced slice.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or: ArcRef<[i32]> = ArcRef::new(Arc::from(vec![1, 2, 3]));
+    ///     let other = or.clone().cow_set(0, 10);
+    ///
+    ///     assert_eq!(&*or, &[1, 2, 3]);
+    ///     assert_eq!(&*other, &[10, 2, 3]);
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn cow_set(self, index: usize, value: T) -> ArcRef<[T]> {
+        let len = self.len();
+        assert!(index < len, "index {} out of bounds for length {}", index, len);
+        let (mut owner, range) = match subslice_range(&self.owner, &self) {
+            Some(range) => (self.owner, range),
+            None => (Arc::from(&*self), 0..len),
+        };
+        Arc::make_mut(&mut owner)[range.start + index] = value;
+        let reference: *const [T] = &owner[range];
+        OwningRef {
+            owner,
+            reference,
+            location: self.location,
+        }
+    }
+}
+
+#[cfg(feature = "nightly")]
+impl<T: Clone> OwningRef<Cow<'static, [T]>, [T]> {
+    /// Copies the slice of the `Cow`, borrowed or owned, into an `Arc`,
+    /// for an owning reference that can be shared regardless of where
+    /// the slice came from.
+    ///
+    /// The result refers to the same part of the slice as `self`. If the
+    /// referenced slice is not part of the owner's, only it is copied.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{ArcRef, CowRef};
+    /// use std::borrow::Cow;
+    ///
+    /// fn main() {
+    /// # #[cfg(feature = "nightly")]
+    /// # {
+    ///     static DATA: [i32; 3] = [1, 2, 3];
+    ///     let or: CowRef<'static, [i32]> = CowRef::new(Cow::Borrowed(&DATA[..]));
+    ///     let or: ArcRef<[i32]> = or.map(|s| &s[1..]).cow_slice_to_arc();
+    ///
+    ///     assert_eq!(&*or, &[2, 3]);
+    ///     assert_eq!(&**or.owner(), &[1, 2, 3]);
+    /// # }
+    /// }
+    /// ```
+    pub fn cow_slice_to_arc(self) -> ArcRef<[T]> {
+        let (owner, range): (Arc<[T]>, _) = match subslice_range(&self.owner, &self) {
+            Some(range) => (Arc::from(&*self.owner), range),
+            None => (Arc::from(&*self), 0..self.len()),
+        };
+        let reference: *const [T] = &owner[range];
+        OwningRef {
+            owner,
+            reference,
+            location: self.location,
+        }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> OwningRef<Rc<T>, U> {
+    /// Returns the number of strong pointers to the owner's allocation,
+    /// including the one of `self`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::RcRef;
+    /// use std::rc::Rc;
+    ///
+    /// fn main() {
+    ///     let or = RcRef::new(Rc::new(42));
+    ///     let clone = or.clone();
+    ///
+    ///     assert_eq!(or.owner_strong_count(), 2);
+    ///     drop(clone);
+    ///     assert_eq!(or.owner_strong_count(), 1);
+    /// }
+    /// ```
+    pub fn owner_strong_count(&self) -> usize {
+        Rc::strong_count(&self.owner)
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> OwningRef<Arc<T>, U> {
+    /// Returns the number of strong pointers to the owner's allocation,
+    /// including the one of `self`.
+    ///
+    /// Other threads can change the count at any time,
+    /// so this is only meant for diagnostics.
+    pub fn owner_strong_count(&self) -> usize {
+        Arc::strong_count(&self.owner)
+    }
+
+    /// Creates a weak owning reference to the same referent,
+    /// that does not keep the owner's allocation alive.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or = ArcRef::new(Arc::new([1, 2])).map(|a| &a[1]);
+    ///     let weak = or.downgrade();
+    ///
+    ///     assert_eq!(*weak.upgrade().unwrap(), 2);
+    ///     drop(or);
+    ///     assert!(weak.upgrade().is_none());
+    /// }
+    /// ```
+    pub fn downgrade(&self) -> WeakOwningRef<T, U> {
+        WeakOwningRef {
+            owner: Arc::downgrade(&self.owner),
+            reference: self.reference,
+        }
+    }
+
+    /// Discards the reference and makes the owner's target mutable,
+    /// cloning it first if the `Arc` is shared, like `Arc::make_mut`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let shared = Arc::new([1, 2, 3]);
+    ///     let or = ArcRef::new(shared.clone()).map(|a| &a[1]);
+    ///
+    ///     let mut or = or.make_mut_owner();
+    ///     or[1] = 20;
+    ///
+    ///     assert_eq!(*or, [1, 20, 3]);
+    ///     assert_eq!(*shared, [1, 2, 3]);
+    /// }
+    /// ```
+    pub fn make_mut_owner(self) -> OwningRefMut<UniqueArc<T>, T>
+        where T: Clone,
+    {
+        let mut owner = self.owner;
+        Arc::make_mut(&mut owner);
+        OwningRefMut::new(UniqueArc(owner))
+    }
+
+    /// Moves the owner's target into a `Box` if `self` holds the only
+    /// strong pointer to it, or returns `self` unchanged otherwise.
+    ///
+    /// A projection into the target is moved along with it, like with `into_shared`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{ArcRef, BoxRef};
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let or = ArcRef::new(Arc::new((1, 2))).map(|t| &t.1);
+    ///     let or: BoxRef<(i32, i32), i32> = or.try_unwrap_to_box().ok().unwrap();
+    ///
+    ///     assert_eq!(*or, 2);
+    /// }
+    /// ```
+    pub fn try_unwrap_to_box(self) -> Result<OwningRef<Box<T>, U>, Self>
+        where T: Sized, U: Relocate,
+    {
+        let offset = offset_in(&*self, &*self.owner);
+        let owner = match Arc::try_unwrap(self.owner) {
+            Ok(target) => Box::new(target),
+            Err(owner) => return Err(OwningRef {
+                owner,
+                reference: self.reference,
+                location: self.location,
+            }),
+        };
+        Ok(OwningRef {
+            reference: unsafe { move_reference(self.reference, offset, &*owner) },
+            owner,
+            location: self.location,
+        })
+    }
+}
+
+impl<T: ?Sized> OwningRef<Arc<T>, T> {
+    /// Returns a mutable reference to the owner's target if `self` holds
+    /// the only pointer to it, like `Arc::get_mut`.
+    ///
+    /// As the mutation could invalidate where `self` points to, `self` is
+    /// reset to refer to the owner's target as a whole in that case.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::ArcRef;
+    /// use std::sync::Arc;
+    ///
+    /// fn main() {
+    ///     let mut or = ArcRef::new(Arc::new(1));
+    ///     *or.get_mut_owner().unwrap() += 1;
+    ///     assert_eq!(*or, 2);
+    ///
+    ///     let _other = or.clone();
+    ///     assert!(or.get_mut_owner().is_none());
+    /// }
+    /// ```
+    pub fn get_mut_owner(&mut self) -> Option<&mut T> {
+        Arc::get_mut(&mut self.owner)?;
+        // Reset the reference through the owner before taking the unique
+        // borrow, so that writes through the returned `&mut T` do not
+        // invalidate it, as they would a pointer derived from that borrow.
+        self.reference = Arc::as_ptr(&self.owner);
+        Arc::get_mut(&mut self.owner)
+    }
+}
+
+impl<O, H> OwningHandle<O, H>
+    where O: StableAddress, H: Deref,
+{
+    /// Creates a new owning handle, creating the handle from
+    /// a pointer to the owner's target with `f`.
+    ///
+    /// The pointer stays valid for as long as the handle lives.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::OwningHandle;
+    /// use std::cell::RefCell;
+    ///
+    /// fn main() {
+    ///     let cell = Box::new(RefCell::new(vec![1, 2]));
+    ///     let mut handle = OwningHandle::new_with_fn(cell, |c| unsafe { (*c).borrow_mut() });
+    ///     handle.push(3);
+    ///
+    ///     assert_eq!(*handle, [1, 2, 3]);
+    /// }
+    /// ```
+    pub fn new_with_fn<F>(o: O, f: F) -> Self
+        where F: FnOnce(*const O::Target) -> H
+    {
+        OwningHandle {
+            handle: f(&*o),
+            _owner: o,
+        }
+    }
+
+    /// Creates a new owning handle like `new_with_fn`,
+    /// with `f` being able to fail.
+    pub fn try_new<F, E>(o: O, f: F) -> Result<Self, E>
+        where F: FnOnce(*const O::Target) -> Result<H, E>
+    {
+        Ok(OwningHandle {
+            handle: f(&*o)?,
+            _owner: o,
+        })
+    }
+}
+
+/// Where an owning reference was last created by `map`.
+///
+/// Only tracked with the `debug-locations` feature in debug builds,
+/// and zero sized otherwise.
+#[derive(Clone, Copy)]
+struct MapLocation {
+    #[cfg(all(feature = "debug-locations", debug_assertions))]
+    location: Option<&'static Location<'static>>,
+}
+
+impl MapLocation {
+    const NONE: MapLocation = MapLocation {
+        #[cfg(all(feature = "debug-locations", debug_assertions))]
+        location: None,
+    };
+
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    fn caller() -> MapLocation {
+        MapLocation {
+            #[cfg(all(feature = "debug-locations", debug_assertions))]
+            location: Some(Location::caller()),
+        }
+    }
+
+    #[cfg(feature = "debug-locations")]
+    fn get(self) -> Option<&'static Location<'static>> {
+        #[cfg(debug_assertions)]
+        return self.location;
+        #[cfg(not(debug_assertions))]
+        return None;
+    }
+}
+
+/// Boxes `value` and bundles it with `owner`,
+/// for results that are computed from a referent instead of pointing into it.
+fn bundle<O, U>(owner: O, value: U) -> OwningRef<(O, Box<U>), U> {
+    let value = Box::new(value);
+    OwningRef {
+        reference: &*value,
+        owner: (owner, value),
+        location: MapLocation::NONE,
+    }
+}
+
+/// Returns the offset of `reference` into `base`, if it points into it.
+fn offset_in<U: ?Sized, T: ?Sized>(reference: &U, base: &T) -> Option<usize> {
+    let start = reference as *const U as *const u8 as usize;
+    let end = start + mem::size_of_val(reference);
+    let base_start = base as *const T as *const u8 as usize;
+    if start < base_start || end > base_start + mem::size_of_val(base) {
+        return None;
+    }
+    Some(start - base_start)
+}
+
+/// Moves `reference` to `offset` into `base`, or leaves it untouched.
+///
+/// Used for carrying a projection along when the value it points into
+/// is moved, with `offset` computed by `offset_in` before the move.
+unsafe fn move_reference<U: ?Sized + Relocate, T: ?Sized>(reference: *const U, offset: Option<usize>, base: &T)
+    -> *const U
+{
+    match offset {
+        Some(offset) => U::relocate(reference, (base as *const T as *const u8).add(offset)),
+        None => reference,
+    }
+}
+
+/// Returns the range of indices `sub` occupies in `base`,
+/// or `None` if it does not lie within it.
+fn subslice_range<T>(base: &[T], sub: &[T]) -> Option<Range<usize>> {
+    let size = mem::size_of::<T>();
+    if size == 0 {
+        return None;
+    }
+    let base_start = base.as_ptr() as usize;
+    let start = sub.as_ptr() as usize;
+    if start < base_start || start + mem::size_of_val(sub) > base_start + mem::size_of_val(base) {
+        return None;
+    }
+    let offset = (start - base_start) / size;
+    Some(offset..offset + sub.len())
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// std traits
+/////////////////////////////////////////////////////////////////////////////
+
+use std::ops::{Deref, DerefMut};
+use std::convert::From;
+use std::iter;
+use std::mem;
+use std::ops::Range;
+use std::str;
+use std::cmp;
+use std::fmt::{self, Debug};
+use std::marker::{Send, Sync};
+use std::panic;
+use std::thread;
+#[cfg(feature = "debug-locations")]
+use std::panic::Location;
+
+impl<O, T: ?Sized> Deref for OwningRef<O, T> {
+    type Target = T;
+
+    // This is the hot path, and compiles to a single load of `reference`
+    // without any checks or branches, as the owner is never consulted
+    // and `location` is zero sized unless `debug-locations` is enabled.
+    fn deref(&self) -> &T {
+        unsafe {
+            &*self.reference
+        }
+    }
+}
+
+impl<O, T: ?Sized> From<O> for OwningRef<O, T>
+    where O: StableAddress, O: Deref<Target = T>,
+{
+    fn from(owner: O) -> Self {
+        OwningRef::new(owner)
+    }
+}
+
+// ^ FIXME: Is a Into impl for calling into_inner() possible as well?
+
+impl<T, U: ?Sized + Relocate> From<OwningRef<Box<T>, U>> for OwningRef<Arc<T>, U> {
+    /// Moves the boxed value into an `Arc`, like `into_shared` does into an `Rc`.
+    fn from(or: OwningRef<Box<T>, U>) -> Self {
+        let offset = offset_in(&*or, &*or.owner);
+        let owner = Arc::new(*or.owner);
+        OwningRef {
+            reference: unsafe { move_reference(or.reference, offset, &*owner) },
+            owner,
+            location: or.location,
+        }
+    }
+}
+
+impl<T, U: ?Sized + Relocate> From<OwningRef<Box<T>, U>> for OwningRef<Rc<T>, U> {
+    /// Moves the boxed value into an `Rc`, like `into_shared`.
+    fn from(or: OwningRef<Box<T>, U>) -> Self {
+        or.into_shared()
+    }
+}
+
+impl<T> From<OwningRef<Vec<T>, [T]>> for OwningRef<Box<[T]>, [T]> {
+    /// Converts the owner into a boxed slice, like `vec_ref_into_boxed`.
+    fn from(or: OwningRef<Vec<T>, [T]>) -> Self {
+        or.vec_ref_into_boxed()
+    }
+}
+
+impl<T> From<OwningRef<Box<[T]>, [T]>> for OwningRef<Vec<T>, [T]> {
+    /// Converts the owner into a vector, like `boxed_ref_into_vec`.
+    fn from(or: OwningRef<Box<[T]>, [T]>) -> Self {
+        or.boxed_ref_into_vec()
+    }
+}
+
+/// The `Debug` representation of a string or slice,
+/// cut off after `max` characters or elements.
+struct Truncated<'a, T: ?Sized> {
+    value: &'a T,
+    max: usize,
+}
+
+impl Debug for Truncated<'_, str> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.value.char_indices().nth(self.max) {
+            Some((end, _)) => write!(f, "{:?}...", &self.value[..end]),
+            None => write!(f, "{:?}", self.value),
+        }
+    }
+}
+
+impl<T: Debug> Debug for Truncated<'_, [T]> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if self.value.len() <= self.max {
+            return f.debug_list().entries(self.value).finish();
+        }
+        f.debug_list().entries(&self.value[..self.max]).finish_non_exhaustive()
+    }
+}
+
+// An owning reference points at something kept alive by its owner,
+// which does not move along with the owning reference.
+unsafe impl<O: StableAddress, T: ?Sized> StableAddress for OwningRef<O, T> {}
+unsafe impl<O: CloneStableAddress, T: ?Sized> CloneStableAddress for OwningRef<O, T> {}
+
+unsafe impl<O: IntoRootOwner, T: ?Sized> IntoRootOwner for OwningRef<O, T> {
+    type Root = O::Root;
+    fn into_root_owner(self) -> O::Root {
+        self.owner.into_root_owner()
+    }
+}
+
+impl<O, T: ?Sized> Debug for OwningRef<O, T>
+    where O: Debug, T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "OwningRef {{ owner: {:?}, reference: {:?} }}",
+               self.owner(), &**self)
+    }
+}
+
+impl<O, T: ?Sized> Clone for OwningRef<O, T>
+    where O: CloneStableAddress,
+{
+    fn clone(&self) -> Self {
+        OwningRef {
+            owner: self.owner.clone(),
+            reference: self.reference,
+            location: self.location,
+        }
+    }
+}
+
+impl<O, T: ?Sized> PartialEq for OwningRef<O, T>
+    where T: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        (**self).eq(&**other)
+    }
+}
+
+impl<O, T: ?Sized> Eq for OwningRef<O, T>
+    where T: Eq,
+{}
+
+impl<O, T: ?Sized> PartialOrd for OwningRef<O, T>
+    where T: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<O, T: ?Sized> Ord for OwningRef<O, T>
+    where T: Ord,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+unsafe impl<O: Send, T: ?Sized + Sync> Send for OwningRef<O, T> {}
+unsafe impl<O: Sync, T: ?Sized + Sync> Sync for OwningRef<O, T> {}
+
+impl<O, H> Deref for OwningHandle<O, H>
+    where O: StableAddress, H: Deref,
+{
+    type Target = H::Target;
+
+    fn deref(&self) -> &H::Target {
+        &self.handle
+    }
+}
+
+impl<O, H> DerefMut for OwningHandle<O, H>
+    where O: StableAddress, H: DerefMut,
+{
+    fn deref_mut(&mut self) -> &mut H::Target {
+        &mut self.handle
+    }
+}
+
+impl<O, T: ?Sized> Deref for OwningRefMut<O, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe {
+            &*self.reference
+        }
+    }
+}
+
+impl<O, T: ?Sized> DerefMut for OwningRefMut<O, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {
+            &mut *self.reference
+        }
+    }
+}
+
+impl<O, T: ?Sized> From<O> for OwningRefMut<O, T>
+    where O: StableAddress, O: DerefMut<Target = T>,
+{
+    fn from(owner: O) -> Self {
+        OwningRefMut::new(owner)
+    }
+}
+
+impl<O, T: ?Sized> Debug for OwningRefMut<O, T>
+    where O: Debug, T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "OwningRefMut {{ owner: {:?}, reference: {:?} }}",
+               self.owner(), &**self)
+    }
+}
+
+unsafe impl<O: Send, T: ?Sized + Send> Send for OwningRefMut<O, T> {}
+unsafe impl<O: Sync, T: ?Sized + Sync> Sync for OwningRefMut<O, T> {}
+
+impl Debug for dyn Erased {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "<Erased>",)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// std types integration and convenience type defs
+/////////////////////////////////////////////////////////////////////////////
+
+use std::boxed::Box;
+use std::cell::OnceCell;
+use std::pin::Pin;
+use std::borrow::{Borrow, Cow, ToOwned};
+use std::collections::{HashMap, HashSet};
+use std::ffi::{CStr, OsStr};
+use std::path::Path;
+use std::hash::{BuildHasher, Hash};
+use std::rc::Rc;
+use std::slice::SliceIndex;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, Weak};
+
+unsafe impl<T: ?Sized> StableAddress for Box<T> {}
+unsafe impl<T> StableAddress for Vec<T> {}
+unsafe impl StableAddress for String {}
+#[cfg(feature = "nightly")]
+unsafe impl<T: ?Sized> StableAddress for Rc<T> {}
+#[cfg(feature = "nightly")]
+unsafe impl<T: ?Sized> StableAddress for Arc<T> {}
+#[cfg(feature = "nightly")]
+unsafe impl<T: ?Sized> CloneStableAddress for Rc<T> {}
+#[cfg(feature = "nightly")]
+unsafe impl<T: ?Sized> CloneStableAddress for Arc<T> {}
+
+#[cfg(not(feature = "nightly"))]
+unsafe impl<T> CloneStableAddress for Rc<T> {}
+#[cfg(not(feature = "nightly"))]
+unsafe impl<T> CloneStableAddress for Arc<T> {}
+#[cfg(not(feature = "nightly"))]
+unsafe impl<T> StableAddress for Rc<T> {}
+#[cfg(not(feature = "nightly"))]
+unsafe impl<T> StableAddress for Arc<T> {}
+
+unsafe impl<T: ?Sized> StableAddress for &T {}
+unsafe impl<T: ?Sized> CloneStableAddress for &T {}
+
+macro_rules! root_owner {
+    ($($ty:ty, [$($param:tt)*];)*) => {$(
+        unsafe impl<$($param)*> IntoRootOwner for $ty {
+            type Root = Self;
+            fn into_root_owner(self) -> Self { self }
+        }
+    )*}
+}
+
+root_owner! {
+    Box<T>, [T: ?Sized];
+    Vec<T>, [T];
+    String, [];
+    Rc<T>, [T: ?Sized];
+    Arc<T>, [T: ?Sized];
+    &'a T, ['a, T: ?Sized];
+}
+
+// `Cow` dereferences an owned value through `Borrow::borrow`, which an
+// arbitrary `ToOwned` impl may point into the `Cow` itself, so only the
+// std pairs known to borrow from a heap buffer are stable.
+unsafe impl<'a> StableAddress for Cow<'a, str> {}
+unsafe impl<'a, T: Clone> StableAddress for Cow<'a, [T]> {}
+unsafe impl<'a> StableAddress for Cow<'a, CStr> {}
+unsafe impl<'a> StableAddress for Cow<'a, OsStr> {}
+unsafe impl<'a> StableAddress for Cow<'a, Path> {}
+
+unsafe impl<T> Relocate for T {
+    fn relocate(_: *const T, addr: *const u8) -> *const T { addr as *const T }
+}
+unsafe impl<T> Relocate for [T] {
+    fn relocate(reference: *const [T], addr: *const u8) -> *const [T] {
+        ptr::slice_from_raw_parts(addr as *const T, reference.len())
+    }
+}
+unsafe impl Relocate for str {
+    fn relocate(reference: *const str, addr: *const u8) -> *const str {
+        ptr::slice_from_raw_parts(addr, (reference as *const [u8]).len()) as *const str
+    }
+}
+
+impl<T, I: SliceIndex<[T]>> Project<I> for [T] {
+    type Output = I::Output;
+    #[track_caller]
+    fn project(&self, idx: I) -> &I::Output { &self[idx] }
+    fn try_project(&self, idx: I) -> Option<&I::Output> { self.get(idx) }
+}
+impl<T, I: SliceIndex<[T]>> Project<I> for Vec<T> {
+    type Output = I::Output;
+    #[track_caller]
+    fn project(&self, idx: I) -> &I::Output { &self[idx] }
+    fn try_project(&self, idx: I) -> Option<&I::Output> { self.get(idx) }
+}
+impl<K, V, Q: ?Sized, S> Project<&Q> for HashMap<K, V, S>
+    where K: Eq + Hash + Borrow<Q>, Q: Eq + Hash, S: BuildHasher,
+{
+    type Output = V;
+    #[track_caller]
+    fn project(&self, key: &Q) -> &V { self.get(key).expect("no entry found for key") }
+    fn try_project(&self, key: &Q) -> Option<&V> { self.get(key) }
+}
+
+/// Typedef of a owning reference that uses a `Box` as the owner.
+pub type BoxRef<T, U = T> = OwningRef<Box<T>, U>;
+/// Typedef of a owning reference that uses a `Vec` as the owner.
+pub type VecRef<T, U = T> = OwningRef<Vec<T>, U>;
+/// Typedef of a owning reference that uses a `String` as the owner.
+pub type StringRef = OwningRef<String, str>;
+/// Typedef of a owning reference that uses a `Rc` as the owner.
+pub type RcRef<T, U = T> = OwningRef<Rc<T>, U>;
+/// Typedef of a owning reference that uses a `Arc` as the owner.
+pub type ArcRef<T, U = T> = OwningRef<Arc<T>, U>;
+/// Typedef of a owning reference that uses a `Cow` as the owner.
+///
+/// This works for `Cow<str>`, `Cow<[T]>`, `Cow<CStr>`, `Cow<OsStr>` and
+/// `Cow<Path>`. Others, like `Cow<Box<T>>`, may store their target inline
+/// and can not be owners:
+///
+/// ```compile_fail
+/// extern crate owning_ref;
+/// use owning_ref::CowRef;
+/// use std::borrow::Cow;
+///
+/// fn main() {
+///     let or: CowRef<Box<i32>> = CowRef::new(Cow::Owned(Box::new(5)));
+/// }
+/// ```
+pub type CowRef<'a, T, U = T> = OwningRef<Cow<'a, T>, U>;
+/// Typedef of a owning reference that borrows its owner instead of owning it.
+pub type RefOwningRef<'a, T, U = T> = OwningRef<&'a T, U>;
+
+/// Typedef of a owning handle that holds the guard of a mutex it shares ownership of.
+pub type ArcMutexHandle<T> = OwningHandle<Arc<Mutex<T>>, MutexGuard<'static, T>>;
+/// Typedef of a owning handle that holds the read guard of a `RwLock` it shares ownership of.
+pub type ArcRwLockReadHandle<T> = OwningHandle<Arc<RwLock<T>>, RwLockReadGuard<'static, T>>;
+/// Typedef of a owning handle that holds the write guard of a `RwLock` it shares ownership of.
+pub type ArcRwLockWriteHandle<T> = OwningHandle<Arc<RwLock<T>>, RwLockWriteGuard<'static, T>>;
+
+/// Creates an owning reference to a slice by moving the elements of `v` into an `Arc`.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::arc_ref_from_vec;
+///
+/// fn main() {
+/// # #[cfg(feature = "nightly")]
+/// # {
+///     let or = arc_ref_from_vec(vec![1, 2, 3]).map(|s| &s[1..]);
+///     assert_eq!(&*or, &[2, 3]);
+/// # }
+/// }
+/// ```
+#[cfg(feature = "nightly")]
+pub fn arc_ref_from_vec<T>(v: Vec<T>) -> ArcRef<[T]> {
+    OwningRef::new(Arc::from(v))
+}
+
+/// Creates an owning reference to a slice by collecting `iter` into an `Arc`.
+///
+/// Trusted-length iterators, like ranges or `slice::Iter`, are written
+/// straight into the `Arc`'s allocation, without going through a `Vec` first.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::arc_ref_from_iter;
+///
+/// fn main() {
+/// # #[cfg(feature = "nightly")]
+/// # {
+///     let or = arc_ref_from_iter(1..4).map(|s| &s[1..]);
+///     assert_eq!(&*or, &[2, 3]);
+/// # }
+/// }
+/// ```
+#[cfg(feature = "nightly")]
+pub fn arc_ref_from_iter<I: IntoIterator>(iter: I) -> ArcRef<[I::Item]> {
+    OwningRef::new(iter.into_iter().collect())
+}
+
+/// Creates an owning reference to a copy of `s` in an `Arc`,
+/// with a single allocation.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::arc_str_ref;
+///
+/// fn main() {
+/// # #[cfg(feature = "nightly")]
+/// # {
+///     let or = arc_str_ref("hello world").map(|s| &s[6..]);
+///     assert_eq!(&*or, "world");
+/// # }
+/// }
+/// ```
+#[cfg(feature = "nightly")]
+pub fn arc_str_ref(s: &str) -> ArcRef<str> {
+    OwningRef::new(Arc::from(s))
+}
+
+/// Collects owning references into an owning reference to a boxed slice of them.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{ArcRef, collect_refs};
+/// use std::sync::Arc;
+///
+/// fn main() {
+///     let refs = collect_refs(vec![ArcRef::new(Arc::new(1)), ArcRef::new(Arc::new(2))]);
+///     let sum: i32 = refs.iter().map(|r| **r).sum();
+///
+///     assert_eq!(sum, 3);
+/// }
+/// ```
+pub fn collect_refs<T: ?Sized, U: ?Sized>(refs: Vec<ArcRef<T, U>>) -> BoxRef<[ArcRef<T, U>]> {
+    OwningRef::new(refs.into_boxed_slice())
+}
+
+/// Locks the mutex `arc` points at without blocking,
+/// bundling the guard with the `Arc`.
+///
+/// Returns the `Arc` back if the mutex is locked elsewhere or poisoned.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::try_lock_arc;
+/// use std::sync::{Arc, Mutex};
+///
+/// fn main() {
+///     let arc = Arc::new(Mutex::new(1));
+///     let mut handle = try_lock_arc(arc.clone()).ok().unwrap();
+///     *handle += 1;
+///
+///     assert!(try_lock_arc(arc.clone()).is_err());
+///     drop(handle);
+///     assert_eq!(*arc.lock().unwrap(), 2);
+/// }
+/// ```
+pub fn try_lock_arc<T: 'static>(arc: Arc<Mutex<T>>)
+    -> Result<ArcMutexHandle<T>, Arc<Mutex<T>>>
+{
+    let mutex: *const Mutex<T> = &*arc;
+    // The guard outlives neither the handle nor the `Arc` keeping the mutex alive.
+    let guard = match unsafe { (*mutex).try_lock() } {
+        Ok(guard) => guard,
+        Err(_) => return Err(arc),
+    };
+    Ok(OwningHandle {
+        handle: guard,
+        _owner: arc,
+    })
+}
+
+/// Locks the `RwLock` `arc` points at for reading,
+/// bundling the read guard with the `Arc`.
+///
+/// Blocks until no writer holds the lock. The handle is `Sync` if `T` is,
+/// but like the guard it wraps, it is not `Send`.
+///
+/// # Panics
+///
+/// Panics if the lock is poisoned.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::read_arc;
+/// use std::sync::{Arc, RwLock};
+///
+/// fn main() {
+///     let arc = Arc::new(RwLock::new((1, 2)));
+///     let first = read_arc(arc.clone());
+///     let second = read_arc(arc.clone());
+///
+///     assert_eq!(first.0 + second.1, 3);
+/// }
+/// ```
+#[track_caller]
+pub fn read_arc<T: 'static>(arc: Arc<RwLock<T>>) -> ArcRwLockReadHandle<T> {
+    let lock: *const RwLock<T> = &*arc;
+    // The guard outlives neither the handle nor the `Arc` keeping the lock alive.
+    let guard = unsafe { (*lock).read() }.expect("RwLock is poisoned");
+    OwningHandle {
+        handle: guard,
+        _owner: arc,
+    }
+}
+
+/// Locks the `RwLock` `arc` points at for writing,
+/// bundling the write guard with the `Arc`.
+///
+/// Blocks until no other reader or writer holds the lock.
+///
+/// # Panics
+///
+/// Panics if the lock is poisoned.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{read_arc, write_arc};
+/// use std::sync::{Arc, RwLock};
+///
+/// fn main() {
+///     let arc = Arc::new(RwLock::new((1, 2)));
+///     let mut handle = write_arc(arc.clone());
+///     handle.1 = 20;
+///     drop(handle);
+///
+///     assert_eq!(read_arc(arc).1, 20);
+/// }
+/// ```
+#[track_caller]
+pub fn write_arc<T: 'static>(arc: Arc<RwLock<T>>) -> ArcRwLockWriteHandle<T> {
+    let lock: *const RwLock<T> = &*arc;
+    // The guard outlives neither the handle nor the `Arc` keeping the lock alive.
+    let guard = unsafe { (*lock).write() }.expect("RwLock is poisoned");
+    OwningHandle {
+        handle: guard,
+        _owner: arc,
+    }
+}
+
+/// A kind of owner, for containers that are generic over
+/// how the values they create owning references to are owned.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{ArcOwnership, OwningRef, Ownership};
+///
+/// fn first_word<Own: Ownership>(s: String) -> OwningRef<Own::Owner<String>, str> {
+///     Own::new_ref(s).map(|s| s.split(' ').next().unwrap())
+/// }
+///
+/// fn main() {
+///     let or = first_word::<ArcOwnership>("hello world".to_string());
+///     assert_eq!(&*or, "hello");
+/// }
+/// ```
+pub trait Ownership {
+    /// The owner of a `T`.
+    type Owner<T>: StableAddress<Target = T>;
+
+    /// Moves `value` into a new owner.
+    fn own<T>(value: T) -> Self::Owner<T>;
+
+    /// Moves `value` into a new owner,
+    /// creating an owning reference to it.
+    fn new_ref<T>(value: T) -> OwningRef<Self::Owner<T>, T> {
+        OwningRef::new(Self::own(value))
+    }
+}
+
+/// Values owned by a `Box`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoxOwnership;
+
+/// Values shared by `Rc`s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RcOwnership;
+
+/// Values shared by `Arc`s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ArcOwnership;
+
+impl Ownership for BoxOwnership {
+    type Owner<T> = Box<T>;
+    fn own<T>(value: T) -> Box<T> { Box::new(value) }
+}
+
+impl Ownership for RcOwnership {
+    type Owner<T> = Rc<T>;
+    fn own<T>(value: T) -> Rc<T> { Rc::new(value) }
+}
+
+impl Ownership for ArcOwnership {
+    type Owner<T> = Arc<T>;
+    fn own<T>(value: T) -> Arc<T> { Arc::new(value) }
+}
+
+unsafe impl<'a, T: 'a> IntoErased<'a> for Box<T> {
+    type Erased = Box<dyn Erased + 'a>;
+    fn into_erased(self) -> Self::Erased { self }
+}
+#[cfg(feature = "nightly")]
+unsafe impl<'a, T: 'a> IntoErased<'a> for Rc<T> {
+    type Erased = Rc<dyn Erased + 'a>;
+    fn into_erased(self) -> Self::Erased { self }
+}
+#[cfg(feature = "nightly")]
+unsafe impl<'a, T: 'a> IntoErased<'a> for Arc<T> {
+    type Erased = Arc<dyn Erased + 'a>;
+    fn into_erased(self) -> Self::Erased { self }
+}
+
+unsafe impl<'a, T: Send + Sync + 'a> IntoSyncErased<'a> for Box<T> {
+    type Erased = Box<dyn Erased + Send + Sync + 'a>;
+    fn into_sync_erased(self) -> Self::Erased { self }
+}
+#[cfg(feature = "nightly")]
+unsafe impl<'a, T: Send + Sync + 'a> IntoSyncErased<'a> for Arc<T> {
+    type Erased = Arc<dyn Erased + Send + Sync + 'a>;
+    fn into_sync_erased(self) -> Self::Erased { self }
+}
+
+/// Typedef of a owning reference that uses an erased `Box` as the owner.
+pub type ErasedBoxRef<U> = OwningRef<Box<dyn Erased>, U>;
+/// Typedef of a owning reference that uses an erased `'static` `Box` as the owner.
+///
+/// This is the same type as `ErasedBoxRef`, with the `'static` bound spelled out.
+pub type StaticOwningRef<U> = OwningRef<Box<dyn Erased + 'static>, U>;
+/// Typedef of a owning reference that uses an erased `Rc` as the owner.
+#[cfg(feature = "nightly")]
+pub type ErasedRcRef<U> = OwningRef<Rc<dyn Erased>, U>;
+/// Typedef of a owning reference that uses an erased `Arc` as the owner.
+#[cfg(feature = "nightly")]
+pub type ErasedArcRef<U> = OwningRef<Arc<dyn Erased>, U>;
+/// Typedef of a owning reference that uses an erased, thread safe `Box` as the owner.
+pub type SyncErasedBoxRef<U> = OwningRef<Box<SyncErased>, U>;
+/// Typedef of a owning reference that uses an erased, thread safe `Arc` as the owner.
+#[cfg(feature = "nightly")]
+pub type SyncErasedArcRef<U> = OwningRef<Arc<SyncErased>, U>;
+
+/////////////////////////////////////////////////////////////////////////////
+// third party owner types
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "either")]
+unsafe impl<L, R> StableAddress for either::Either<L, R>
+    where L: StableAddress, R: StableAddress<Target = L::Target> {}
+#[cfg(feature = "either")]
+unsafe impl<L, R> CloneStableAddress for either::Either<L, R>
+    where L: CloneStableAddress, R: CloneStableAddress<Target = L::Target> {}
+
+#[cfg(feature = "memmap2")]
+unsafe impl StableAddress for memmap2::Mmap {}
+
+/// Typedef of a owning reference that uses a memory map as the owner.
+#[cfg(feature = "memmap2")]
+pub type MmapRef<U = [u8]> = OwningRef<memmap2::Mmap, U>;
+
+#[cfg(feature = "spin")]
+unsafe impl<'a, T: ?Sized> StableAddress for spin::MutexGuard<'a, T> {}
+#[cfg(feature = "spin")]
+unsafe impl<'a, T: ?Sized> StableAddress for spin::RwLockReadGuard<'a, T> {}
+#[cfg(feature = "spin")]
+unsafe impl<'a, T: ?Sized> StableAddress for spin::RwLockWriteGuard<'a, T> {}
+
+/// Typedef of a owning reference that uses a `spin::MutexGuard` as the owner.
+#[cfg(feature = "spin")]
+pub type SpinMutexGuardRef<'a, T, U = T> = OwningRef<spin::MutexGuard<'a, T>, U>;
+/// Typedef of a mutable owning reference that uses a `spin::MutexGuard` as the owner.
+#[cfg(feature = "spin")]
+pub type SpinMutexGuardRefMut<'a, T, U = T> = OwningRefMut<spin::MutexGuard<'a, T>, U>;
+/// Typedef of a owning reference that uses a `spin::RwLockReadGuard` as the owner.
+#[cfg(feature = "spin")]
+pub type SpinRwLockReadGuardRef<'a, T, U = T> = OwningRef<spin::RwLockReadGuard<'a, T>, U>;
+/// Typedef of a mutable owning reference that uses a `spin::RwLockWriteGuard` as the owner.
+#[cfg(feature = "spin")]
+pub type SpinRwLockWriteGuardRefMut<'a, T, U = T> = OwningRefMut<spin::RwLockWriteGuard<'a, T>, U>;
+
+#[cfg(feature = "parking_lot")]
+unsafe impl<'a, T: ?Sized> StableAddress for parking_lot::RwLockUpgradableReadGuard<'a, T> {}
+#[cfg(feature = "parking_lot")]
+unsafe impl<'a, T: ?Sized> StableAddress for parking_lot::RwLockWriteGuard<'a, T> {}
+
+#[cfg(feature = "parking_lot")]
+impl<'a, T: ?Sized, U: ?Sized> OwningRef<parking_lot::RwLockUpgradableReadGuard<'a, T>, U> {
+    /// Atomically upgrades the guarding read lock to a write lock,
+    /// converting `self` into a mutable owning reference to the whole locked value.
+    ///
+    /// The read-only projection can not be carried over,
+    /// so it has to be redone with `map_mut` if needed. This blocks
+    /// until all other readers have released the lock.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// # #[cfg(feature = "parking_lot")]
+    /// extern crate parking_lot;
+    ///
+    /// fn main() {
+    /// # #[cfg(feature = "parking_lot")]
+    /// # {
+    ///     use owning_ref::OwningRef;
+    ///     use parking_lot::RwLock;
+    ///
+    ///     let lock = RwLock::new((1, 2));
+    ///     let or = OwningRef::new(lock.upgradable_read()).map(|t| &t.1);
+    ///     assert_eq!(*or, 2);
+    ///
+    ///     let mut or = or.upgrade().map_mut(|t| &mut t.1);
+    ///     *or = 3;
+    ///     drop(or);
+    ///
+    ///     assert_eq!(*lock.read(), (1, 3));
+    /// # }
+    /// }
+    /// ```
+    pub fn upgrade(self) -> OwningRefMut<parking_lot::RwLockWriteGuard<'a, T>, T> {
+        OwningRefMut::new(parking_lot::RwLockUpgradableReadGuard::upgrade(self.owner))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// projection builder
+/////////////////////////////////////////////////////////////////////////////
+
+use std::marker::PhantomData;
+
+/// Helper trait for the projections collected by an `OwningRefBuilder`,
+/// which are applied once when it is built.
+pub trait ProjectOnce<T: ?Sized> {
+    /// The type of what is projected to.
+    type Output: ?Sized;
+    /// Applies the projection.
+    fn apply(self, t: &T) -> &Self::Output;
+}
+
+/// Helper trait for projections stored and composed as values,
+/// as applied by `OwningRef::apply`.
+///
+/// A projection is only borrowed when applied, so it can be reused.
+pub trait Projection<T: ?Sized> {
+    /// The type of what is projected to.
+    type Output: ?Sized;
+    /// Applies the projection.
+    fn project<'a>(&self, t: &'a T) -> &'a Self::Output;
+}
+
+impl<T: ?Sized, P: ?Sized + Projection<T>> Projection<T> for &P {
+    type Output = P::Output;
+
+    fn project<'a>(&self, t: &'a T) -> &'a P::Output {
+        (**self).project(t)
+    }
+}
+
+/// A builder for an owning reference that collects projections,
+/// applying all of them at once on `build`.
+///
+/// See `OwningRef::builder` for an example.
+pub struct OwningRefBuilder<O, P> {
+    owner: O,
+    projection: P,
+}
+
+impl<O, T: ?Sized> OwningRef<O, T> {
+    /// Starts building an owning reference that points at
+    /// something reachable from the target of `o`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, OwningRef};
+    ///
+    /// fn main() {
+    ///     let or: BoxRef<[[i32; 2]; 2], i32> = OwningRef::builder(Box::new([[1, 2], [3, 4]]))
+    ///         .project(|a| &a[1])
+    ///         .project(|a| &a[0])
+    ///         .build();
+    ///
+    ///     assert_eq!(*or, 3);
+    /// }
+    /// ```
+    pub fn builder(o: O) -> OwningRefBuilder<O, impl ProjectOnce<T, Output = T>>
+        where O: StableAddress,
+              O: Deref<Target = T>,
+    {
+        OwningRefBuilder {
+            owner: o,
+            projection: Identity,
+        }
+    }
+}
+
+impl<O, P> OwningRefBuilder<O, P>
+    where O: StableAddress,
+          P: ProjectOnce<O::Target>,
+{
+    /// Adds a projection from the current referent to something reachable from it.
+    pub fn project<G, U: ?Sized>(self, g: G)
+        -> OwningRefBuilder<O, impl ProjectOnce<O::Target, Output = U>>
+        where G: FnOnce(&P::Output) -> &U
+    {
+        OwningRefBuilder {
+            owner: self.owner,
+            projection: Then {
+                first: self.projection,
+                then: g,
+                marker: PhantomData,
+            },
+        }
+    }
+
+    /// Applies all projections, creating the owning reference.
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn build(self) -> OwningRef<O, P::Output> {
+        let projection = self.projection;
+        OwningRef::new(self.owner).map(|t| projection.apply(t))
+    }
+}
+
+struct Identity;
+
+impl<T: ?Sized> ProjectOnce<T> for Identity {
+    type Output = T;
+
+    fn apply(self, t: &T) -> &T {
+        t
+    }
+}
+
+struct Then<P, G, U: ?Sized> {
+    first: P,
+    then: G,
+    marker: PhantomData<fn() -> *const U>,
+}
+
+impl<T: ?Sized, P, G, U: ?Sized> ProjectOnce<T> for Then<P, G, U>
+    where P: ProjectOnce<T>,
+          G: FnOnce(&P::Output) -> &U,
+{
+    type Output = U;
+
+    fn apply(self, t: &T) -> &U {
+        // The intermediate referent lives at least as long as `t` does,
+        // but that can not be spelled out for the associated type.
+        let first: *const P::Output = self.first.apply(t);
+        let then: *const U = (self.then)(unsafe { &*first });
+        unsafe { &*then }
+    }
+}
+
+/// A projection applying `A` and then `B` to what `A` projected to.
+///
+/// See `OwningRef::apply` for an example.
+pub struct Compose<A, B> {
+    first: A,
+    then: B,
+}
+
+impl<A, B> Compose<A, B> {
+    /// Creates the composition of `first` and `then`.
+    pub fn new(first: A, then: B) -> Self {
+        Compose {
+            first,
+            then,
+        }
+    }
+}
+
+impl<T: ?Sized, A, B> Projection<T> for Compose<A, B>
+    where A: Projection<T>,
+          B: Projection<A::Output>,
+{
+    type Output = B::Output;
+
+    fn project<'a>(&self, t: &'a T) -> &'a B::Output {
+        // Same as for `Then`, the intermediate referent lives as long as `t`.
+        let first: *const A::Output = self.first.project(t);
+        let then: *const B::Output = self.then.project(unsafe { &*first });
+        unsafe { &*then }
+    }
+}
+
+impl<O, T: ?Sized> OwningRef<O, T> {
+    /// Converts `self` into a new owning reference that points at
+    /// what the projection `p` projects the referent to.
+    ///
+    /// This is like `map` for projections stored and composed as values.
+    /// Passing a reference to a projection keeps it around for reuse.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, Compose, Projection};
+    ///
+    /// struct Second;
+    ///
+    /// impl Projection<(i32, [i32; 3])> for Second {
+    ///     type Output = [i32; 3];
+    ///     fn project<'a>(&self, t: &'a (i32, [i32; 3])) -> &'a [i32; 3] { &t.1 }
+    /// }
+    ///
+    /// struct Last;
+    ///
+    /// impl Projection<[i32; 3]> for Last {
+    ///     type Output = i32;
+    ///     fn project<'a>(&self, a: &'a [i32; 3]) -> &'a i32 { &a[2] }
+    /// }
+    ///
+    /// fn main() {
+    ///     let last_of_second = Compose::new(Second, Last);
+    ///
+    ///     let a = BoxRef::new(Box::new((1, [2, 3, 4]))).apply(&last_of_second);
+    ///     let b = BoxRef::new(Box::new((5, [6, 7, 8]))).apply(&last_of_second);
+    ///     assert_eq!((*a, *b), (4, 8));
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn apply<P>(self, p: P) -> OwningRef<O, P::Output>
+        where O: StableAddress,
+              P: Projection<T>,
+    {
+        self.map(|t| p.project(t))
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// wrapper types
+/////////////////////////////////////////////////////////////////////////////
+
+use std::any::Any;
+#[cfg(feature = "nightly")]
+use std::any::TypeId;
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::mem::ManuallyDrop;
+
+/// An owning reference bundled with a precomputed description of its referent.
+///
+/// The description is computed once at construction, which makes it
+/// cheap to log the referent over and over again.
+pub struct Described<R> {
+    inner: R,
+    description: String,
+}
+
+impl<O, T: ?Sized> Described<OwningRef<O, T>> {
+    /// Wraps an owning reference, computing the description
+    /// of its referent with `f`.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, Described};
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new([1, 2, 3, 4])).map(|a| &a[2]);
+    ///     let or = Described::new(or, |x| format!("third element: {}", x));
+    ///
+    ///     assert_eq!(**or, 3);
+    ///     assert_eq!(or.description(), "third element: 3");
+    /// }
+    /// ```
+    pub fn new<F>(inner: OwningRef<O, T>, f: F) -> Self
+        where F: FnOnce(&T) -> String
+    {
+        Described {
+            description: f(&inner),
+            inner,
+        }
+    }
+
+    /// A getter for the cached description.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Discards the description and retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, T> {
+        self.inner
+    }
+}
+
+impl<R> Deref for Described<R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.inner
+    }
+}
+
+/// An owning reference that is only kept around to keep its owner alive.
+///
+/// It does not dereference to the referent, and implements none of the
+/// comparison traits, which makes it clear that it is only stored for
+/// lifetime management.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{BoxRef, JustOwner};
+///
+/// fn main() {
+///     let mut kept = vec![
+///         JustOwner::new(BoxRef::new(Box::new(1))),
+///         JustOwner::new(BoxRef::new(Box::new(2))),
+///     ];
+///     assert_eq!(*kept.pop().unwrap().into_inner(), 2);
+/// }
+/// ```
+pub struct JustOwner<R> {
+    inner: R,
+}
+
+impl<O, T: ?Sized> JustOwner<OwningRef<O, T>> {
+    /// Wraps an owning reference.
+    pub fn new(inner: OwningRef<O, T>) -> Self {
+        JustOwner { inner }
+    }
+
+    /// Retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, T> {
+        self.inner
+    }
+}
+
+/// The result of `OwningRef::map_cow`, either an owning reference
+/// borrowing from the owner or a value computed without it.
+pub enum MapCow<O, T: ?Sized + ToOwned> {
+    /// A projection borrowing from the owner.
+    Borrowed(OwningRef<O, T>),
+    /// A computed value, with the owner dropped.
+    Owned(T::Owned),
+}
+
+impl<O, T: ?Sized + ToOwned> Deref for MapCow<O, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            MapCow::Borrowed(ref or) => or,
+            MapCow::Owned(ref owned) => owned.borrow(),
+        }
+    }
+}
+
+/// A weak owning reference, created by `OwningRef::downgrade` from an `ArcRef`.
+///
+/// This does not keep the owner's allocation alive,
+/// and has to be upgraded for accessing the referent.
+pub struct WeakOwningRef<T: ?Sized, U: ?Sized = T> {
+    owner: Weak<T>,
+    reference: *const U,
+}
+
+impl<T: ?Sized, U: ?Sized> WeakOwningRef<T, U> {
+    /// Attempts to create an owning reference sharing ownership
+    /// of the allocation, returning `None` if it has been dropped already.
+    pub fn upgrade(&self) -> Option<ArcRef<T, U>> {
+        self.owner.upgrade().map(|owner| OwningRef {
+            owner,
+            reference: self.reference,
+            location: MapLocation::NONE,
+        })
+    }
+
+    /// Returns whether the allocation has been dropped already.
+    pub fn is_expired(&self) -> bool {
+        self.owner.strong_count() == 0
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Clone for WeakOwningRef<T, U> {
+    fn clone(&self) -> Self {
+        WeakOwningRef {
+            owner: self.owner.clone(),
+            reference: self.reference,
+        }
+    }
+}
+
+unsafe impl<T: ?Sized, U: ?Sized> Send for WeakOwningRef<T, U>
+    where ArcRef<T, U>: Send {}
+unsafe impl<T: ?Sized, U: ?Sized> Sync for WeakOwningRef<T, U>
+    where ArcRef<T, U>: Sync {}
+
+/// An owning reference that counts how often its referent is accessed.
+///
+/// Only accesses through `get` are counted, which is why this
+/// deliberately does not implement `Deref`.
+pub struct Instrumented<R> {
+    inner: R,
+    accesses: Cell<usize>,
+}
+
+impl<O, T: ?Sized> Instrumented<OwningRef<O, T>> {
+    /// Wraps an owning reference, starting with an access count of zero.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, Instrumented};
+    ///
+    /// fn main() {
+    ///     let or = Instrumented::new(BoxRef::new(Box::new(42)));
+    ///
+    ///     assert_eq!(*or.get(), 42);
+    ///     assert_eq!(*or.get(), 42);
+    ///     assert_eq!(or.access_count(), 2);
+    /// }
+    /// ```
+    pub fn new(inner: OwningRef<O, T>) -> Self {
+        Instrumented {
+            inner,
+            accesses: Cell::new(0),
+        }
+    }
+
+    /// Returns the referent, counting the access.
+    pub fn get(&self) -> &T {
+        self.accesses.set(self.accesses.get() + 1);
+        &self.inner
+    }
+
+    /// Returns how often the referent has been accessed through `get`.
+    pub fn access_count(&self) -> usize {
+        self.accesses.get()
+    }
+
+    /// Discards the access count and retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, T> {
+        self.inner
+    }
+}
+
+/// A registry of erased `Arc` owning references, keyed by their referent type.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{ArcRef, TypedRegistry};
+/// use std::sync::Arc;
+///
+/// fn main() {
+/// # #[cfg(feature = "nightly")]
+/// # {
+///     let mut registry = TypedRegistry::new();
+///     let name = ArcRef::new(Arc::new("widget".to_string())).map(|s| &s[..]);
+///     registry.insert(name.erase_owner());
+///
+///     assert_eq!(&*registry.get::<str>().unwrap(), "widget");
+///     assert!(registry.get::<[u8]>().is_none());
+/// # }
+/// }
+/// ```
+#[cfg(feature = "nightly")]
+#[derive(Default)]
+pub struct TypedRegistry {
+    refs: HashMap<TypeId, Box<dyn Any>>,
+}
+
+#[cfg(feature = "nightly")]
+impl TypedRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        TypedRegistry {
+            refs: HashMap::new(),
+        }
+    }
+
+    /// Inserts an owning reference, returning the one
+    /// previously registered for the referent type `T`.
+    pub fn insert<T: ?Sized + 'static>(&mut self, r: ErasedArcRef<T>) -> Option<ErasedArcRef<T>> {
+        self.refs.insert(TypeId::of::<T>(), Box::new(r))
+            .map(|old| *old.downcast().unwrap())
+    }
+
+    /// Returns a clone of the owning reference registered for the referent type `T`.
+    pub fn get<T: ?Sized + 'static>(&self) -> Option<ErasedArcRef<T>> {
+        self.refs.get(&TypeId::of::<T>())
+            .map(|r| r.downcast_ref::<ErasedArcRef<T>>().unwrap().clone())
+    }
+}
+
+/// An owner wrapper that runs a hook after the wrapped owner has been dropped.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{OwningRef, OnDrop};
+/// use std::sync::Arc;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// fn main() {
+///     let dropped = Arc::new(AtomicBool::new(false));
+///     let flag = dropped.clone();
+///
+///     let owner = OnDrop::new(Box::new([1, 2, 3]), move || flag.store(true, Ordering::SeqCst));
+///     let or = OwningRef::new(owner).map(|a| &a[1]);
+///     assert_eq!(*or, 2);
+///
+///     drop(or);
+///     assert!(dropped.load(Ordering::SeqCst));
+/// }
+/// ```
+pub struct OnDrop<O> {
+    inner: ManuallyDrop<O>,
+    hook: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl<O> OnDrop<O> {
+    /// Wraps `inner`, calling `hook` once it has been dropped.
+    ///
+    /// The hook has to be `Send`, since the last owner to go away
+    /// may be dropped on another thread.
+    pub fn new<F>(inner: O, hook: F) -> Self
+        where F: FnOnce() + Send + 'static
+    {
+        OnDrop {
+            inner: ManuallyDrop::new(inner),
+            hook: Some(Box::new(hook)),
+        }
+    }
+}
+
+impl<O: Deref> Deref for OnDrop<O> {
+    type Target = O::Target;
+
+    fn deref(&self) -> &O::Target {
+        &self.inner
+    }
+}
+
+impl<O> Drop for OnDrop<O> {
+    fn drop(&mut self) {
+        unsafe {
+            ManuallyDrop::drop(&mut self.inner);
+        }
+        if let Some(hook) = self.hook.take() {
+            hook();
+        }
+    }
+}
+
+unsafe impl<O: StableAddress> StableAddress for OnDrop<O> {}
+
+// The hook is only ever touched through `&mut self` in `drop`,
+// so sharing an `OnDrop` never shares the hook.
+unsafe impl<O: Sync> Sync for OnDrop<O> {}
+
+type DropCallback = Arc<Mutex<Option<Box<dyn FnOnce() + Send>>>>;
+
+/// A handle for registering a callback that runs once the owner of an
+/// owning reference created by `OwningRef::map_with_guard` has been dropped.
+///
+/// This is useful for telling foreign code that data it was handed
+/// pointers into is gone. The callback runs whether or not the guard
+/// itself is still around at that point.
+#[must_use]
+pub struct DropGuard {
+    callback: DropCallback,
+}
+
+impl DropGuard {
+    /// Registers `callback`, replacing any previously registered one.
+    pub fn register<F>(&self, callback: F)
+        where F: FnOnce() + Send + 'static
+    {
+        let callback: Box<dyn FnOnce() + Send> = Box::new(callback);
+        let previous = self.callback.lock().unwrap_or_else(PoisonError::into_inner).replace(callback);
+        drop(previous);
+    }
+}
+
+impl<O, T: ?Sized> OwningRef<O, T> {
+    /// Converts `self` into a new owning reference like `map`, returning
+    /// it along with a guard for registering a callback to run once the
+    /// owner has been dropped.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    /// use std::sync::Arc;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// fn main() {
+    ///     let freed = Arc::new(AtomicBool::new(false));
+    ///     let flag = freed.clone();
+    ///
+    ///     let (or, guard) = BoxRef::new(Box::new([1, 2, 3])).map_with_guard(|a| &a[1]);
+    ///     guard.register(move || flag.store(true, Ordering::SeqCst));
+    ///     assert_eq!(*or, 2);
+    ///
+    ///     drop(or);
+    ///     assert!(freed.load(Ordering::SeqCst));
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_with_guard<U: ?Sized, F>(self, f: F) -> (OwningRef<OnDrop<O>, U>, DropGuard)
+        where O: StableAddress,
+              F: FnOnce(&T) -> &U
+    {
+        let callback = DropCallback::default();
+        let hook = callback.clone();
+        let reference: *const U = f(&self);
+        let owner = OnDrop::new(self.owner, move || {
+            let callback = hook.lock().unwrap_or_else(PoisonError::into_inner).take();
+            if let Some(callback) = callback {
+                callback();
+            }
+        });
+        let or = OwningRef {
+            reference,
+            owner,
+            location: MapLocation::caller(),
+        };
+        (or, DropGuard { callback })
+    }
+}
+
+/// An `Arc` known to be the only pointer to its allocation,
+/// which makes it a mutable owner.
+///
+/// It is created by `OwningRef::make_mut_owner`, and never gives out
+/// clones of the `Arc` until it is turned back into one with `into_arc`.
+pub struct UniqueArc<T: ?Sized>(Arc<T>);
+
+impl<T> UniqueArc<T> {
+    /// Moves `value` into a new, unique `Arc`.
+    pub fn new(value: T) -> Self {
+        UniqueArc(Arc::new(value))
+    }
+}
+
+impl<T: ?Sized> UniqueArc<T> {
+    /// Gives up uniqueness, returning the plain `Arc`.
+    pub fn into_arc(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T: ?Sized> Deref for UniqueArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> DerefMut for UniqueArc<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        Arc::get_mut(&mut self.0).expect("UniqueArc is always unique")
+    }
+}
+
+unsafe impl<T: ?Sized> StableAddress for UniqueArc<T> {}
+
+/// An owner bundled with a side table lazily computed from its target.
+///
+/// The cache is boxed, so owning references can point into it as well
+/// as into the owner's target. It starts out empty when created by
+/// `OwningRef::with_cache`, is built by the first `OwningRef::map_with_cache`
+/// projecting into it, and is never rebuilt afterwards.
+pub struct WithCache<O, C> {
+    owner: O,
+    cache: Option<Box<C>>,
+}
+
+impl<O, C> WithCache<O, C> {
+    /// A getter for the cache, or `None` if it has not been built yet.
+    pub fn cache(&self) -> Option<&C> {
+        self.cache.as_deref()
+    }
+
+    /// Discards the cache and retrieves the owner.
+    pub fn into_inner(self) -> O {
+        self.owner
+    }
+}
+
+impl<O: Deref, C> Deref for WithCache<O, C> {
+    type Target = O::Target;
+
+    fn deref(&self) -> &O::Target {
+        &self.owner
+    }
+}
+
+unsafe impl<O: StableAddress, C> StableAddress for WithCache<O, C> {}
+
+impl<O, T: ?Sized> OwningRef<O, T> {
+    /// Bundles the owner of `self` with an empty cache,
+    /// to be built by the first `map_with_cache` projecting into it.
+    pub fn with_cache<C>(self) -> OwningRef<WithCache<O, C>, T> {
+        OwningRef {
+            reference: self.reference,
+            owner: WithCache {
+                owner: self.owner,
+                cache: None,
+            },
+            location: self.location,
+        }
+    }
+}
+
+impl<O, C, T: ?Sized> OwningRef<WithCache<O, C>, T> {
+    /// Converts `self` into a new owning reference pointing into the cache with `project`.
+    ///
+    /// If the cache has not been built yet, it is first computed from the referent
+    /// with `build_cache`. Otherwise `build_cache` is not called, and the existing
+    /// cache is projected into, so it is built at most once per owner.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new("a b c".to_string())).with_cache();
+    ///     assert!(or.owner().cache().is_none());
+    ///
+    ///     let or = or.map_with_cache(|s| s.split(' ').collect::<Vec<_>>().concat(),
+    ///                                |c| &c[1..]);
+    ///
+    ///     assert_eq!(&*or, "bc");
+    ///     assert_eq!(or.owner().cache().unwrap(), "abc");
+    ///     assert_eq!(&**or.owner(), "a b c");
+    /// }
+    /// ```
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn map_with_cache<U: ?Sized, B, P>(self, build_cache: B, project: P)
+        -> OwningRef<WithCache<O, C>, U>
+        where B: FnOnce(&T) -> C,
+              P: FnOnce(&C) -> &U
+    {
+        let mut owner = self.owner;
+        if owner.cache.is_none() {
+            // The referent does not lie in the empty cache,
+            // so filling it in leaves the referent untouched.
+            let cache = build_cache(unsafe { &*self.reference });
+            owner.cache = Some(Box::new(cache));
+        }
+        let reference: *const U = project(owner.cache.as_ref().unwrap());
+        OwningRef {
+            reference,
+            owner,
+            location: MapLocation::caller(),
+        }
+    }
+}
+
+/// An owner that either shares its value or owns it outright,
+/// deciding at runtime how clones of it are made.
+///
+/// Cloning a `Shared` owner only clones the `Arc`, while cloning an
+/// `Owned` one deep copies the boxed value. Because the latter moves
+/// the target, this is not a `CloneStableAddress`, and owning references
+/// using it are cloned with `OwningRef::clone_adaptive` instead.
+#[derive(Debug)]
+pub enum SharedOrOwned<T> {
+    /// A value shared with other owners, cloned cheaply.
+    Shared(Arc<T>),
+    /// A value owned outright, cloned by copying it.
+    Owned(Box<T>),
+}
+
+impl<T: Clone> Clone for SharedOrOwned<T> {
+    fn clone(&self) -> Self {
+        match *self {
+            SharedOrOwned::Shared(ref arc) => SharedOrOwned::Shared(arc.clone()),
+            SharedOrOwned::Owned(ref boxed) => SharedOrOwned::Owned(boxed.clone()),
+        }
+    }
+}
+
+impl<T> Deref for SharedOrOwned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match *self {
+            SharedOrOwned::Shared(ref arc) => arc,
+            SharedOrOwned::Owned(ref boxed) => boxed,
+        }
+    }
+}
+
+unsafe impl<T> StableAddress for SharedOrOwned<T> {}
+
+impl<T: Clone, U: ?Sized> OwningRef<SharedOrOwned<T>, U> {
+    /// Clones the owning reference, sharing the owner if it is `Shared`
+    /// and copying it if it is `Owned`, in which case the projection
+    /// is carried over to the copy.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the owner is `Owned` and the referent does not lie
+    /// within the boxed value itself, for example behind a `String` in it,
+    /// since the copy would still point into the original. This includes
+    /// `'static` projections, which can not be told apart from those.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{OwningRef, SharedOrOwned};
+    ///
+    /// fn main() {
+    ///     let or = OwningRef::new(SharedOrOwned::Owned(Box::new([1, 2, 3]))).map(|a| &a[1]);
+    ///     let copy = or.clone_adaptive();
+    ///
+    ///     assert_eq!(*copy, 2);
+    ///     assert!(!std::ptr::eq(&*or, &*copy));
+    /// }
+    /// ```
+    #[track_caller]
+    pub fn clone_adaptive(&self) -> Self
+        where U: Relocate,
+    {
+        let owner = self.owner.clone();
+        let reference = match owner {
+            SharedOrOwned::Shared(_) => self.reference,
+            SharedOrOwned::Owned(ref boxed) => {
+                let offset = offset_in(&**self, &*self.owner);
+                assert!(offset.is_some(), "referent does not lie within the owned value");
+                unsafe { move_reference(self.reference, offset, &**boxed) }
+            }
+        };
+        OwningRef {
+            reference,
+            owner,
+            location: self.location,
+        }
+    }
+}
+
+/// A reference whose owner has been leaked on purpose,
+/// created by `OwningRef::leak`.
+///
+/// This spells out the leak in the type, instead of a plain `&'static T`
+/// that does not tell where it came from.
+#[derive(Debug)]
+pub struct Leaked<T: ?Sized + 'static>(&'static T);
+
+impl<T: ?Sized> Leaked<T> {
+    /// Retrieves the `'static` reference.
+    pub fn into_inner(self) -> &'static T {
+        self.0
+    }
+}
+
+impl<T: ?Sized> Clone for Leaked<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Leaked<T> {}
+
+impl<T: ?Sized> Deref for Leaked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+impl<O, T: ?Sized> OwningRef<O, T> {
+    /// Leaks the owner, so that the referent lives for the rest of the program.
+    ///
+    /// The owner is never dropped, and none of its resources are released.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::BoxRef;
+    ///
+    /// fn main() {
+    ///     let or: BoxRef<String, str> = BoxRef::new(Box::new("hello world".to_string()))
+    ///         .map(|s| &s[6..]);
+    ///     let leaked = or.leak();
+    ///
+    ///     let s: &'static str = leaked.into_inner();
+    ///     assert_eq!(s, "world");
+    /// }
+    /// ```
+    pub fn leak(self) -> Leaked<T>
+        where O: 'static,
+              T: 'static
+    {
+        let reference = self.reference;
+        mem::forget(self.owner);
+        Leaked(unsafe { &*reference })
+    }
+}
+
+/// An owner of nothing, for wrapping `'static` references
+/// uniformly with ones that do own their referent.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StaticOwner;
+
+impl<T: ?Sized> OwningRef<StaticOwner, T> {
+    /// Creates an owning reference to constant data.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, ErasedBoxRef, OwningRef};
+    ///
+    /// fn main() {
+    ///     let refs: Vec<ErasedBoxRef<str>> = vec![
+    ///         OwningRef::constant("constant").erase_owner(),
+    ///         BoxRef::new(Box::new("owned".to_string())).map(|s| &s[..]).erase_owner(),
+    ///     ];
+    ///
+    ///     assert_eq!(&*refs[0], "constant");
+    /// }
+    /// ```
+    pub fn constant(value: &'static T) -> Self {
+        OwningRef {
+            owner: StaticOwner,
+            reference: value,
+            location: MapLocation::NONE,
+        }
+    }
+}
+
+impl Deref for StaticOwner {
+    type Target = ();
+
+    fn deref(&self) -> &() {
+        &()
+    }
+}
+
+unsafe impl StableAddress for StaticOwner {}
+unsafe impl CloneStableAddress for StaticOwner {}
+
+unsafe impl<'a> IntoErased<'a> for StaticOwner {
+    type Erased = Box<dyn Erased + 'a>;
+    fn into_erased(self) -> Self::Erased {
+        Box::new(self)
+    }
+}
+
+/// An owning reference to a slice that caches the slice's length.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{VecRef, SlicedOwningRef};
+///
+/// fn main() {
+///     let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3, 4]).map(|v| &v[1..]);
+///     let or = SlicedOwningRef::new(or);
+///
+///     assert_eq!(or.len(), 3);
+///     assert_eq!(or[0], 2);
+/// }
+/// ```
+pub struct SlicedOwningRef<O, T> {
+    inner: OwningRef<O, [T]>,
+    len: usize,
+}
+
+impl<O, T> SlicedOwningRef<O, T> {
+    /// Wraps an owning reference, caching the length of its referent.
+    pub fn new(inner: OwningRef<O, [T]>) -> Self {
+        SlicedOwningRef {
+            len: inner.len(),
+            inner,
+        }
+    }
+
+    /// Returns the cached length of the referenced slice.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the referenced slice is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Discards the cached length and retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, [T]> {
+        self.inner
+    }
+}
+
+impl<O, T> Deref for SlicedOwningRef<O, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.inner
+    }
+}
+
+/// A cursor reading from the byte slice referent of an owning reference.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{BoxRef, OwningRefCursor};
+/// use std::io::Read;
+///
+/// fn main() {
+///     let or = BoxRef::new(Box::new(*b"hello world")).map(|a| &a[..]);
+///     let mut cursor = OwningRefCursor::new(or);
+///
+///     let mut hello = [0; 5];
+///     cursor.read_exact(&mut hello).unwrap();
+///     assert_eq!(&hello, b"hello");
+///     assert_eq!(cursor.position(), 5);
+/// }
+/// ```
+pub struct OwningRefCursor<O> {
+    inner: OwningRef<O, [u8]>,
+    pos: usize,
+}
+
+impl<O> OwningRefCursor<O> {
+    /// Wraps an owning reference, starting at the beginning of its referent.
+    pub fn new(inner: OwningRef<O, [u8]>) -> Self {
+        OwningRefCursor {
+            inner,
+            pos: 0,
+        }
+    }
+
+    /// Returns the number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the bytes not read yet.
+    pub fn remaining_slice(&self) -> &[u8] {
+        &self.inner[self.pos..]
+    }
+
+    /// Discards the position and retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, [u8]> {
+        self.inner
+    }
+}
+
+impl<O> Read for OwningRefCursor<O> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining_slice().read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<O> bytes::Buf for OwningRefCursor<O> {
+    fn remaining(&self) -> usize {
+        self.inner.len() - self.pos
+    }
+
+    fn chunk(&self) -> &[u8] {
+        self.remaining_slice()
+    }
+
+    fn advance(&mut self, cnt: usize) {
+        assert!(cnt <= self.remaining(), "cannot advance past the end of the referent");
+        self.pos += cnt;
+    }
+}
+
+/// An owner wrapping a reusable buffer, for parse loops that decode
+/// into the same scratch space over and over again.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::ScratchOwner;
+///
+/// fn main() {
+///     let scratch: ScratchOwner<u8> = ScratchOwner::with_capacity(16);
+///     let or = ScratchOwner::reset_and_project(scratch, |buf| buf.extend(b"key=value"), |s| &s[4..]);
+///     assert_eq!(&*or, b"value");
+///
+///     let or = ScratchOwner::reset_and_project(or.into_inner(), |buf| buf.extend(b"k=v"), |s| &s[2..]);
+///     assert_eq!(&*or, b"v");
+/// }
+/// ```
+#[derive(Default)]
+pub struct ScratchOwner<T> {
+    buf: Vec<T>,
+}
+
+impl<T> ScratchOwner<T> {
+    /// Creates a scratch owner with an empty buffer.
+    pub fn new() -> Self {
+        ScratchOwner {
+            buf: Vec::new(),
+        }
+    }
+
+    /// Creates a scratch owner with an empty buffer
+    /// that can hold `capacity` elements without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        ScratchOwner {
+            buf: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Clears the buffer of `owner`, refills it with `fill` and
+    /// creates an owning reference to what `project` returns from its contents.
+    ///
+    /// The allocation of the buffer is kept across uses.
+    #[cfg_attr(feature = "debug-locations", track_caller)]
+    pub fn reset_and_project<G, F, U: ?Sized>(mut owner: Self, fill: G, project: F)
+        -> OwningRef<ScratchOwner<T>, U>
+        where G: FnOnce(&mut Vec<T>),
+              F: FnOnce(&[T]) -> &U
+    {
+        owner.buf.clear();
+        fill(&mut owner.buf);
+        OwningRef::new(owner).map(project)
+    }
+}
+
+impl<T> Deref for ScratchOwner<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buf
+    }
+}
+
+unsafe impl<T> StableAddress for ScratchOwner<T> {}
+
+/// An owner wrapper that zeroizes what the wrapped owner points at when dropped,
+/// for owning references to secrets.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+///
+/// fn main() {
+/// # #[cfg(feature = "zeroize")]
+/// # {
+///     use owning_ref::{OwningRef, Zeroizing};
+///
+///     let secret = Zeroizing::new(vec![0x13u8, 0x37].into_boxed_slice());
+///     let or = OwningRef::new(secret).map(|s| &s[1]);
+///     assert_eq!(*or, 0x37);
+/// # }
+/// }
+/// ```
+#[cfg(feature = "zeroize")]
+pub struct Zeroizing<O>
+    where O: DerefMut, O::Target: zeroize::Zeroize,
+{
+    inner: O,
+}
+
+#[cfg(feature = "zeroize")]
+impl<O> Zeroizing<O>
+    where O: DerefMut, O::Target: zeroize::Zeroize,
+{
+    /// Wraps `inner`, zeroizing its target once dropped.
+    pub fn new(inner: O) -> Self {
+        Zeroizing {
+            inner,
+        }
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<O> Deref for Zeroizing<O>
+    where O: DerefMut, O::Target: zeroize::Zeroize,
+{
+    type Target = O::Target;
+
+    fn deref(&self) -> &O::Target {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<O> Drop for Zeroizing<O>
+    where O: DerefMut, O::Target: zeroize::Zeroize,
+{
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(&mut *self.inner);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+unsafe impl<O> StableAddress for Zeroizing<O>
+    where O: StableAddress + DerefMut, O::Target: zeroize::Zeroize {}
+
+/// An owning reference bundled with a cache of projections from its referent.
+///
+/// Since the owning reference keeps its referent alive at a stable
+/// address, cached projections stay valid for as long as it lives.
+pub struct Memoized<K, R: Deref> {
+    inner: R,
+    cache: HashMap<K, *const R::Target>,
+}
+
+impl<K: Eq + Hash, O, T: ?Sized> Memoized<K, OwningRef<O, T>> {
+    /// Wraps an owning reference, starting with an empty cache.
+    ///
+    /// # Example
+    /// ```
+    /// extern crate owning_ref;
+    /// use owning_ref::{BoxRef, Memoized};
+    ///
+    /// fn main() {
+    ///     let or = BoxRef::new(Box::new("key=value".to_string())).map(|s| &s[..]);
+    ///     let mut memo = Memoized::new(or);
+    ///
+    ///     assert_eq!(memo.get_or_project("value", |s| &s[4..]), "value");
+    ///     assert_eq!(memo.get_or_project("value", |_| unreachable!()), "value");
+    /// }
+    /// ```
+    pub fn new(inner: OwningRef<O, T>) -> Self {
+        Memoized {
+            inner,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Returns the projection cached for `key`,
+    /// projecting from the referent with `f` and caching the result if there is none.
+    pub fn get_or_project<F>(&mut self, key: K, f: F) -> &T
+        where F: FnOnce(&T) -> &T
+    {
+        let inner = &self.inner;
+        let projected = *self.cache.entry(key).or_insert_with(|| f(inner));
+        unsafe { &*projected }
+    }
+
+    /// Discards the cache and retrieves the owning reference.
+    pub fn into_inner(self) -> OwningRef<O, T> {
+        self.inner
+    }
+}
+
+impl<K, R: Deref> Deref for Memoized<K, R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.inner
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// atomic owning reference
+/////////////////////////////////////////////////////////////////////////////
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// A reader-biased spin cell holding an `ArcRef` that can be loaded and
+/// replaced from multiple threads.
+///
+/// This is not lock-free. Loads never block, but replacing the stored
+/// reference spins until no load that might still observe the previous one
+/// is in flight before releasing it, so a continuous stream of loads can
+/// starve writers indefinitely. It suits values that are read far more often
+/// than they are replaced.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+/// use owning_ref::{ArcRef, AtomicOwningRef};
+/// use std::sync::Arc;
+///
+/// fn main() {
+///     let cell = AtomicOwningRef::new(ArcRef::new(Arc::new([1, 2, 3])).map(|a| &a[0]));
+///     assert_eq!(*cell.load(), 1);
+///
+///     cell.store(ArcRef::new(Arc::new([4, 5, 6])).map(|a| &a[2]));
+///     assert_eq!(*cell.load(), 6);
+/// }
+/// ```
+pub struct AtomicOwningRef<T: ?Sized, U: ?Sized = T> {
+    current: AtomicPtr<ArcRef<T, U>>,
+    readers: AtomicUsize,
+}
+
+impl<T: ?Sized, U: ?Sized> AtomicOwningRef<T, U> {
+    /// Creates a new cell holding `r`.
+    pub fn new(r: ArcRef<T, U>) -> Self {
+        AtomicOwningRef {
+            current: AtomicPtr::new(Box::into_raw(Box::new(r))),
+            readers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a clone of the currently stored owning reference.
+    pub fn load(&self) -> ArcRef<T, U>
+        where Arc<T>: CloneStableAddress,
+    {
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        let r = unsafe { (*self.current.load(Ordering::SeqCst)).clone() };
+        self.readers.fetch_sub(1, Ordering::SeqCst);
+        r
+    }
+
+    /// Replaces the stored owning reference with `new`.
+    pub fn store(&self, new: ArcRef<T, U>) {
+        drop(self.swap(new));
+    }
+
+    /// Replaces the stored owning reference with `new`, returning the previous one.
+    pub fn swap(&self, new: ArcRef<T, U>) -> ArcRef<T, U> {
+        let new = Box::into_raw(Box::new(new));
+        let old = self.current.swap(new, Ordering::SeqCst);
+        self.wait_for_readers();
+        unsafe { *Box::from_raw(old) }
+    }
+
+    /// Replaces the stored owning reference with `new` if it is identical to `current`,
+    /// that is if both share the same owner allocation and point at the same referent.
+    ///
+    /// On success the previous owning reference is returned,
+    /// otherwise `new` is handed back to the caller.
+    pub fn compare_exchange(&self, current: &ArcRef<T, U>, new: ArcRef<T, U>)
+        -> Result<ArcRef<T, U>, ArcRef<T, U>>
+    {
+        let new = Box::into_raw(Box::new(new));
+        loop {
+            // Staying registered as a reader until the exchange is done
+            // keeps `stored` from being freed and reused in the meantime.
+            self.readers.fetch_add(1, Ordering::SeqCst);
+            let stored = self.current.load(Ordering::SeqCst);
+            let identical = unsafe {
+                Arc::ptr_eq((*stored).owner(), current.owner())
+                    && ptr::eq((*stored).reference, current.reference)
+            };
+            if !identical {
+                self.readers.fetch_sub(1, Ordering::SeqCst);
+                return Err(unsafe { *Box::from_raw(new) });
+            }
+            let exchanged = self.current.compare_exchange(stored, new,
+                                                          Ordering::SeqCst,
+                                                          Ordering::SeqCst);
+            self.readers.fetch_sub(1, Ordering::SeqCst);
+            if let Ok(old) = exchanged {
+                self.wait_for_readers();
+                return Ok(unsafe { *Box::from_raw(old) });
+            }
+        }
+    }
+
+    fn wait_for_readers(&self) {
+        while self.readers.load(Ordering::SeqCst) != 0 {
+            std::hint::spin_loop();
+        }
+    }
+}
+
+impl<T: ?Sized, U: ?Sized> Drop for AtomicOwningRef<T, U> {
+    fn drop(&mut self) {
+        unsafe {
+            drop(Box::from_raw(*self.current.get_mut()));
+        }
+    }
+}
+
+unsafe impl<T: ?Sized, U: ?Sized> Send for AtomicOwningRef<T, U>
+    where ArcRef<T, U>: Send + Sync {}
+unsafe impl<T: ?Sized, U: ?Sized> Sync for AtomicOwningRef<T, U>
+    where ArcRef<T, U>: Send + Sync {}
+
+/////////////////////////////////////////////////////////////////////////////
+// test utilities
+/////////////////////////////////////////////////////////////////////////////
+
+/// Checks at runtime that `o` dereferences to the same address
+/// before and after moving it, panicking if it does not.
+///
+/// This is meant for testing custom `StableAddress` implementations.
+/// Passing the check does not prove an implementation correct: it only
+/// covers a single move of the given value, and can not detect targets
+/// that change address through other means, like mutation through
+/// interior mutability.
+///
+/// # Example
+/// ```
+/// extern crate owning_ref;
+///
+/// fn main() {
+/// # #[cfg(feature = "test-util")]
+/// # {
+///     let owner = owning_ref::verify_stable_address(Box::new(42));
+///     assert_eq!(*owner, 42);
+/// # }
+/// }
+/// ```
+#[cfg(feature = "test-util")]
+pub fn verify_stable_address<O: StableAddress>(o: O) -> O {
+    let before = &*o as *const O::Target as *const u8;
+    assert_eq!(before, &*o as *const O::Target as *const u8,
+               "owner dereferences to different addresses");
+    let moved = Box::new(o);
+    assert_eq!(before, &**moved as *const O::Target as *const u8,
+               "owner dereferences to a different address after being moved");
+    *moved
+}
+
+/////////////////////////////////////////////////////////////////////////////
+// macros
 /////////////////////////////////////////////////////////////////////////////
-// std types integration and convenience type defs
-/////////////////////////////////////////////////////////////////////////////
 
-use std::boxed::Box;
-use std::rc::Rc;
-use std::sync::Arc;
+/// Creates a `Vec` of owning references to the listed fields of the referent
+/// of a clonable owning reference, each erased to `dyn Debug`.
+///
+/// # Example
+/// ```
+/// #[macro_use]
+/// extern crate owning_ref;
+/// use owning_ref::RcRef;
+/// use std::rc::Rc;
+///
+/// struct Point { x: i32, y: i32 }
+///
+/// fn main() {
+///     let or = RcRef::new(Rc::new(Point { x: 1, y: 2 }));
+///     let fields = fields_owned!(or; x, y);
+///
+///     let formatted: Vec<String> = fields.iter().map(|f| format!("{:?}", &**f)).collect();
+///     assert_eq!(formatted, ["1", "2"]);
+/// }
+/// ```
+#[macro_export]
+macro_rules! fields_owned {
+    ($or:expr; $($field:tt),*) => {{
+        let or = &$or;
+        vec![$(
+            or.clone().map(|t| &t.$field as &dyn (::std::fmt::Debug))
+        ),*]
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OwningRef, BoxRef, Erased, ErasedBoxRef};
+    use super::{Described, Instrumented};
+    use super::{ArcRef, AtomicOwningRef};
+    use super::VecRef;
+    use super::RcRef;
+    use std::rc::Rc;
+    use super::Project;
+    use super::OnDrop;
+    use super::SlicedOwningRef;
+    use super::OwningRefMut;
+    use super::ScratchOwner;
+    use super::Memoized;
+    use super::MapCow;
+
+    #[derive(Debug, PartialEq)]
+    struct Example(u32, String, [u8; 3]);
+    fn example() -> Example {
+        Example(42, "hello world".to_string(), [1, 2, 3])
+    }
+
+    #[test]
+    fn new_deref() {
+        let or: OwningRef<Box<()>, ()> = OwningRef::new(Box::new(()));
+        assert_eq!(&*or, &());
+    }
+
+    #[test]
+    fn into() {
+        let or: OwningRef<Box<()>, ()> = Box::new(()).into();
+        assert_eq!(&*or, &());
+    }
+
+    #[test]
+    fn map_offset_ref() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or: BoxRef<_, u32> = or.map(|x| &x.0);
+        assert_eq!(&*or, &42);
+
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or: BoxRef<_, u8> = or.map(|x| &x.2[1]);
+        assert_eq!(&*or, &2);
+    }
+
+    #[test]
+    fn map_heap_ref() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or: BoxRef<_, str> = or.map(|x| &x.1[..5]);
+        assert_eq!(&*or, "hello");
+    }
+
+    #[test]
+    fn map_static_ref() {
+        let or: BoxRef<()> = Box::new(()).into();
+        let or: BoxRef<_, str> = or.map(|_| "hello");
+        assert_eq!(&*or, "hello");
+    }
+
+    #[test]
+    fn map_chained() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or: BoxRef<_, str> = or.map(|x| &x[1..5]);
+        let or: BoxRef<_, str> = or.map(|x| &x[..2]);
+        assert_eq!(&*or, "el");
+    }
+
+    #[test]
+    fn map_chained_inference() {
+        let or = BoxRef::new(Box::new(example().1))
+            .map(|x| &x[..5])
+            .map(|x| &x[1..3]);
+        assert_eq!(&*or, "el");
+    }
+
+    #[test]
+    fn owner() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or = or.map(|x| &x[..5]);
+        assert_eq!(&*or, "hello");
+        assert_eq!(&**or.owner(), "hello world");
+    }
+
+    #[test]
+    fn into_inner() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or = or.map(|x| &x[..5]);
+        assert_eq!(&*or, "hello");
+        let s = *or.into_inner();
+        assert_eq!(&s, "hello world");
+    }
+
+    #[test]
+    fn fmt_debug() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or = or.map(|x| &x[..5]);
+        let s = format!("{:?}", or);
+        assert_eq!(&s, "OwningRef { owner: \"hello world\", reference: \"hello\" }");
+    }
+
+    #[test]
+    fn erased_owner() {
+        let o1: BoxRef<Example, str> = BoxRef::new(Box::new(example()))
+            .map(|x| &x.1[..]);
+
+        let o2: BoxRef<String, str> = BoxRef::new(Box::new(example().1))
+            .map(|x| &x[..]);
+
+        let os: Vec<ErasedBoxRef<str>> = vec![o1.erase_owner(), o2.erase_owner()];
+        assert!(os.iter().all(|e| &e[..] == "hello world"));
+    }
+
+    #[test]
+    fn non_static_erased_owner() {
+        let foo = [413, 612];
+        let bar = &foo;
+
+        let o: BoxRef<&[i32; 2]> = Box::new(bar).into();
+        let o: BoxRef<&[i32; 2], i32> = o.map(|a: &&[i32; 2]| &a[0]);
+        let o: BoxRef<dyn Erased, i32> = o.erase_owner();
+
+        assert_eq!(*o, 413);
+    }
+
+    #[test]
+    fn described() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or = Described::new(or.map(|x| &x.1[..5]),
+                                |s| format!("{} ({} bytes)", s, s.len()));
+
+        assert_eq!(&**or, "hello");
+        assert_eq!(or.description(), "hello (5 bytes)");
+
+        let or = or.into_inner();
+        assert_eq!(&*or, "hello");
+    }
+
+    #[test]
+    fn atomic_owning_ref() {
+        use std::sync::{Arc, Barrier};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::thread;
+
+        let first: ArcRef<Example, u32> = ArcRef::new(Arc::new(example())).map(|x| &x.0);
+        let cell = Arc::new(AtomicOwningRef::new(first.clone()));
+
+        let second = Example(7, "bye".to_string(), [0; 3]);
+        let second: ArcRef<Example, u32> = ArcRef::new(Arc::new(second)).map(|x| &x.0);
+        let start = Arc::new(Barrier::new(5));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4).map(|_| {
+            let (cell, start, done) = (cell.clone(), start.clone(), done.clone());
+            thread::spawn(move || {
+                start.wait();
+                while !done.load(Ordering::SeqCst) {
+                    let or = cell.load();
+                    assert!(*or == 42 || *or == 7);
+                }
+            })
+        }).collect();
+
+        // Keep storing while the readers are loading, so that stores have
+        // to wait out loads of the reference they are about to release.
+        start.wait();
+        for i in 0..1000 {
+            cell.store(if i % 2 == 0 { second.clone() } else { first.clone() });
+        }
+        cell.store(second.clone());
+        done.store(true, Ordering::SeqCst);
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(*cell.load(), 7);
+        assert_eq!(Arc::strong_count(first.owner()), 1);
+        assert_eq!(Arc::strong_count(second.owner()), 2);
+
+        let third: ArcRef<Example, u32> = ArcRef::new(Arc::new(example())).map(|x| &x.0);
+        let third = cell.compare_exchange(&first, third).unwrap_err();
+        let previous = cell.compare_exchange(&second, third).unwrap();
+        assert_eq!(*previous, 7);
+        assert_eq!(*cell.load(), 42);
+    }
+
+    #[test]
+    fn lines_owned() {
+        use std::sync::Arc;
+
+        let text: ArcRef<String, str> = ArcRef::new(Arc::new("a\nb\r\nc".to_string()))
+            .map(|s| &s[..]);
+        let lines: Vec<_> = text.lines_owned().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(&*lines[0], "a");
+        assert_eq!(&*lines[1], "b");
+        assert_eq!(&*lines[2], "c");
+        assert_eq!(Arc::strong_count(lines[0].owner()), 3);
+    }
+
+    #[test]
+    fn vec_ref_into_boxed() {
+        let mut v = Vec::with_capacity(16);
+        v.extend_from_slice(&[1, 2, 3, 4]);
+
+        let or: VecRef<i32, [i32]> = VecRef::new(v).map(|v| &v[1..3]);
+        let or = or.vec_ref_into_boxed();
+        assert_eq!(&*or, &[2, 3]);
+        assert_eq!(or.as_ptr(), or.owner()[1..].as_ptr());
+
+        let or = or.boxed_ref_into_vec();
+        assert_eq!(&*or, &[2, 3]);
+        assert_eq!(or.as_ptr(), or.owner()[1..].as_ptr());
+    }
+
+    #[test]
+    fn vec_ref_into_boxed_static_ref() {
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2]).map(|_| &[5, 6, 7][..]);
+        let or = or.vec_ref_into_boxed();
+        assert_eq!(&*or, &[5, 6, 7]);
+    }
+
+    #[test]
+    fn instrumented() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or = Instrumented::new(or.map(|x| &x.2[..]));
+        assert_eq!(or.access_count(), 0);
+
+        for _ in 0..3 {
+            assert_eq!(or.get(), &[1, 2, 3]);
+        }
+        assert_eq!(or.access_count(), 3);
+    }
+
+    #[cfg(feature = "either")]
+    #[test]
+    fn either_owner() {
+        use either::Either;
+        use std::sync::Arc;
+
+        let left: OwningRef<Either<Box<Example>, Arc<Example>>, Example>
+            = OwningRef::new(Either::Left(Box::new(example())));
+        let right: OwningRef<Either<Box<Example>, Arc<Example>>, Example>
+            = OwningRef::new(Either::Right(Arc::new(example())));
+
+        let ors = [left.map(|x| &x.1[6..]), right.map(|x| &x.1[6..])];
+        assert!(ors.iter().all(|or| &**or == "world"));
+    }
+
+    #[test]
+    fn map_aligned() {
+        use std::sync::Arc;
+
+        for start in 0..16 {
+            let or: ArcRef<[u8; 64], [u8]> = ArcRef::new(Arc::new([0; 64]))
+                .map(|a| &a[start..]);
+            let aligned = or.map_aligned::<16>().unwrap();
+            assert_eq!(aligned.as_ptr() as usize % 16, 0);
+            assert!(aligned.len() > 64 - start - 16);
+        }
+
+        // a single unaligned byte followed by at least one more unaligned one
+        let or: ArcRef<[u8; 64], [u8]> = ArcRef::new(Arc::new([0; 64])).map(|a| {
+            let start = (0..16).find(|&i| (a.as_ptr() as usize + i) % 16 == 1).unwrap();
+            &a[start..start + 1]
+        });
+        assert!(or.map_aligned::<16>().is_none());
+
+        // ends right before an aligned byte, or is empty
+        let or: ArcRef<[u8; 64], [u8]> = ArcRef::new(Arc::new([0; 64])).map(|a| {
+            let end = (1..17).find(|&i| (a.as_ptr() as usize + i).is_multiple_of(16)).unwrap();
+            &a[end - 1..end]
+        });
+        assert!(or.map_aligned::<16>().is_none());
+        let or: ArcRef<[u8; 64], [u8]> = ArcRef::new(Arc::new([0; 64])).map(|a| &a[..0]);
+        assert!(or.map_aligned::<16>().is_none());
+    }
+
+    #[cfg(feature = "memmap2")]
+    #[test]
+    fn mmap_owner() {
+        use super::MmapRef;
+        use memmap2::Mmap;
+        use std::env;
+        use std::fs::{self, File};
+
+        let path = env::temp_dir().join(format!("owning_ref_mmap_{}", std::process::id()));
+        fs::write(&path, b"HDR:payload").unwrap();
+
+        let mmap = unsafe { Mmap::map(&File::open(&path).unwrap()).unwrap() };
+        let or: MmapRef = MmapRef::new(mmap).map(|bytes| &bytes[4..]);
+        assert_eq!(&*or, b"payload");
+
+        drop(or);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn try_map_owner() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or: BoxRef<str> = unsafe {
+            or.try_map_owner(|owner| -> Result<_, ((), _)> {
+                let owner = owner.into_boxed_str();
+                let reference = &*owner as *const str;
+                Ok((owner, reference))
+            })
+        }.ok().unwrap();
+        let or = or.map(|s| &s[6..]);
+        assert_eq!(&*or, "world");
+
+        let (err, owner) = unsafe {
+            or.try_map_owner(|owner| -> Result<(Box<str>, *const str), _> {
+                Err(("no", owner))
+            })
+        }.err().unwrap();
+        assert_eq!(err, "no");
+        assert_eq!(&*owner, "hello world");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn typed_registry() {
+        use super::{TypedRegistry, ErasedArcRef};
+        use std::sync::Arc;
+
+        let mut registry = TypedRegistry::new();
+        let s: ErasedArcRef<str> = ArcRef::new(Arc::new(example()))
+            .map(|x| &x.1[..5])
+            .erase_owner();
+        let b: ErasedArcRef<[u8]> = ArcRef::new(Arc::new(example()))
+            .map(|x| &x.2[1..])
+            .erase_owner();
+        assert!(registry.insert(s).is_none());
+        assert!(registry.insert(b).is_none());
+
+        assert_eq!(&*registry.get::<str>().unwrap(), "hello");
+        assert_eq!(&*registry.get::<[u8]>().unwrap(), &[2, 3]);
+        assert!(registry.get::<u32>().is_none());
+
+        let s: ErasedArcRef<str> = ArcRef::new(Arc::new(example()))
+            .map(|x| &x.1[6..])
+            .erase_owner();
+        assert_eq!(&*registry.insert(s).unwrap(), "hello");
+        assert_eq!(&*registry.get::<str>().unwrap(), "world");
+    }
+
+    #[test]
+    fn map_or_owner_target() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or: BoxRef<Example, str> = or.map(|x| &x.1[6..]);
+        assert_eq!(&*or, "world");
+
+        let target: *const Example = &**or.owner();
+        let or: BoxRef<Example> = or.map_or_owner_target(|_| unsafe { &*target });
+        assert_eq!(&*or as *const Example, target);
+        assert_eq!(*or, example());
+
+        let or = or.map_or_owner_target(|x| &x.1[..5]);
+        assert_eq!(&*or, "hello");
+    }
+
+    #[test]
+    fn into_shared() {
+        let or: BoxRef<String> = Box::new(example().1).into();
+        let or: BoxRef<String, str> = or.map(|s| &s[6..]);
+        let or: RcRef<String, str> = or.into_shared();
+
+        let a = or.clone();
+        let b = or.clone();
+        assert_eq!(&*a, "world");
+        assert_eq!(&*b, "world");
+        assert_eq!(Rc::strong_count(or.owner()), 3);
+    }
+
+    #[test]
+    fn into_shared_moves_projection() {
+        let or: BoxRef<Example> = Box::new(example()).into();
+        let or: BoxRef<Example, [u8]> = or.map(|x| &x.2[1..]);
+        let or: RcRef<Example, [u8]> = or.into_shared();
+
+        assert_eq!(&*or, &[2, 3]);
+        assert_eq!(or.as_ptr(), or.owner().2[1..].as_ptr());
+    }
+
+    #[test]
+    fn into_shared_moves_str_projection() {
+        let or: BoxRef<[u8; 5]> = Box::new(*b"hello").into();
+        let or: BoxRef<[u8; 5], str> = or.map(|b| ::std::str::from_utf8(&b[1..4]).unwrap());
+        let or: RcRef<[u8; 5], str> = or.into_shared();
+
+        assert_eq!(&*or, "ell");
+        assert_eq!(or.as_ptr(), or.owner()[1..].as_ptr());
+    }
+
+    #[test]
+    fn project_owned() {
+        use std::collections::HashMap;
+
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3, 4]);
+        let or: VecRef<i32, [i32]> = or.project_owned(1..3);
+        assert_eq!(&*or, &[2, 3]);
+        let or: VecRef<i32, i32> = or.project_owned(1);
+        assert_eq!(*or, 3);
+
+        let or: BoxRef<Vec<i32>> = BoxRef::new(Box::new(vec![1, 2, 3, 4]));
+        let or: BoxRef<Vec<i32>, i32> = or.project_owned(3);
+        assert_eq!(*or, 4);
+
+        let mut map = HashMap::new();
+        map.insert("hello".to_string(), example());
+        let or = BoxRef::new(Box::new(map)).project_owned("hello");
+        assert_eq!(*or, example());
+    }
+
+    #[test]
+    fn project_owned_custom_container() {
+        struct Grid {
+            width: usize,
+            cells: Vec<u8>,
+        }
+
+        impl Project<(usize, usize)> for Grid {
+            type Output = u8;
+            fn project(&self, (x, y): (usize, usize)) -> &u8 {
+                &self.cells[y * self.width + x]
+            }
+        }
+
+        let grid = Grid { width: 3, cells: vec![0, 1, 2, 3, 4, 5] };
+        let or = BoxRef::new(Box::new(grid)).project_owned((1, 1));
+        assert_eq!(*or, 4);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn arc_ref_from_vec() {
+        use std::sync::Arc;
+
+        let or: ArcRef<[i32]> = super::arc_ref_from_vec(vec![1, 2, 3, 4, 5]);
+        let head = or.clone().map(|s| &s[..2]);
+        let tail = or.clone().map(|s| &s[3..]);
+
+        assert_eq!(&*head, &[1, 2]);
+        assert_eq!(&*tail, &[4, 5]);
+        assert_eq!(Arc::strong_count(or.owner()), 3);
+    }
+
+    #[test]
+    fn on_drop_owner() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+
+        let owner = OnDrop::new(Box::new(example()), move || { counter.fetch_add(1, Ordering::SeqCst); });
+        let or: OwningRef<OnDrop<Box<Example>>, str> = OwningRef::new(owner).map(|e| &e.1[..5]);
+
+        assert_eq!(&*or, "hello");
+        assert_eq!(runs.load(Ordering::SeqCst), 0);
+
+        drop(or);
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn overlaps() {
+        use std::sync::Arc;
+
+        let or: ArcRef<Vec<i32>, [i32]> = ArcRef::new(Arc::new(vec![1, 2, 3, 4, 5, 6]))
+            .map(|v| &v[..]);
+
+        let a = or.clone().map(|s| &s[..2]);
+        let b = or.clone().map(|s| &s[2..4]);
+        assert!(!a.overlaps(&b));
+        assert!(!b.overlaps(&a));
+
+        let c = or.clone().map(|s| &s[1..3]);
+        let d = or.clone().map(|s| &s[2..]);
+        assert!(c.overlaps(&d));
+        assert!(d.overlaps(&c));
+
+        let empty = or.clone().map(|s| &s[3..3]);
+        assert!(!empty.overlaps(&or));
+    }
+
+    #[test]
+    fn trait_object_owner() {
+        trait Named {
+            fn name(&self) -> &str;
+            fn greeting(&self) -> String {
+                format!("hello {}", self.name())
+            }
+        }
+
+        impl Named for Example {
+            fn name(&self) -> &str {
+                &self.1
+            }
+        }
+
+        let owner: Box<dyn Named> = Box::new(example());
+        let or = BoxRef::new(owner).map(|n| n.name());
+
+        assert_eq!(&*or, "hello world");
+        assert_eq!(or.owner().greeting(), "hello hello world");
+    }
+
+    #[test]
+    fn sliced_owning_ref() {
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3, 4, 5])
+            .map(|v| &v[1..])
+            .map(|s| &s[..3]);
+        let or = SlicedOwningRef::new(or);
+
+        assert_eq!(or.len(), 3);
+        assert_eq!(or.len(), or.into_inner().len());
+
+        let empty: VecRef<i32, [i32]> = VecRef::new(vec![1]).map(|v| &v[1..]);
+        assert!(SlicedOwningRef::new(empty).is_empty());
+    }
+
+    #[test]
+    fn owning_ref_mut_map_mut() {
+        let or = OwningRefMut::new(Box::new(example()));
+        let mut or = or.map_mut(|e| &mut e.2[1..]);
+        or[0] = 42;
+
+        assert_eq!(&*or, &[42, 3]);
+        assert_eq!(or.owner().2, [1, 42, 3]);
+        assert_eq!(or.into_inner().0, 42);
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn parking_lot_upgrade() {
+        use parking_lot::RwLock;
+
+        let lock = RwLock::new(example());
+        let or = OwningRef::new(lock.upgradable_read()).map(|e| &e.1[..5]);
+        assert_eq!(&*or, "hello");
+
+        assert!(lock.try_read().is_some());
+        assert!(lock.try_upgradable_read().is_none());
+
+        let mut or = or.upgrade().map_mut(|e| &mut e.1);
+        or.push('!');
+        assert!(lock.try_read().is_none());
+        drop(or);
+
+        assert_eq!(lock.read().1, "hello world!");
+    }
+
+    #[test]
+    fn read_ints() {
+        use std::sync::Arc;
+
+        let or: ArcRef<[u8; 9], [u8]> = ArcRef::new(Arc::new([9, 0xde, 0xad, 0xbe, 0xef, 1, 2, 3, 4]))
+            .map(|a| &a[1..]);
+
+        assert_eq!(*or.clone().read_u32_be(), 0xdead_beef);
+        assert_eq!(*or.clone().read_u32_le(), 0xefbe_adde);
+        assert_eq!(*or.clone().read_u16_be(), 0xdead);
+        assert_eq!(*or.clone().read_u64_le(), 0x0403_0201_efbe_adde);
+
+        let or = or.read_u32_be();
+        assert_eq!(Arc::strong_count(&or.owner().0), 1);
+        assert_eq!(or.owner().0[0], 9);
+    }
+
+    #[test]
+    #[should_panic]
+    fn read_int_too_short() {
+        let or: BoxRef<[u8; 3], [u8]> = BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[..]);
+        or.read_u32_be();
+    }
+
+    #[test]
+    fn try_lock_arc() {
+        use std::sync::{Arc, Mutex};
+
+        let arc = Arc::new(Mutex::new(example()));
+        let other = arc.clone();
+        let guard = other.lock().unwrap();
+
+        let arc = match super::try_lock_arc(arc) {
+            Ok(_) => panic!("locked twice"),
+            Err(arc) => arc,
+        };
+        drop(guard);
+        drop(other);
+
+        let mut handle = super::try_lock_arc(arc.clone()).ok().unwrap();
+        handle.0 += 1;
+        drop(handle);
+
+        assert_eq!(arc.lock().unwrap().0, 43);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn iter_owned() {
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let mut map = HashMap::new();
+        map.insert("one".to_string(), 1);
+        map.insert("two".to_string(), 2);
+        map.insert("three".to_string(), 3);
+
+        let or = ArcRef::new(Arc::new(map));
+        let arc = or.owner().clone();
+
+        let mut entries: Vec<_> = or.iter_owned().collect();
+        entries.sort_by_key(|e| *e.1);
+
+        let keys: Vec<&str> = entries.iter().map(|e| &e.0[..]).collect();
+        assert_eq!(keys, ["one", "two", "three"]);
+        assert_eq!(Arc::strong_count(&arc), 7);
+
+        drop(entries);
+        assert_eq!(Arc::strong_count(&arc), 1);
+    }
+
+    #[test]
+    fn nested_box_owner() {
+        let or: BoxRef<Box<i32>, i32> = BoxRef::new(Box::new(Box::new(42))).map(|b| &**b);
+        assert_eq!(*or, 42);
+
+        let or: BoxRef<Box<Example>, str> = BoxRef::new(Box::new(Box::new(example())))
+            .map(|b| &b.1[6..]);
+        assert_eq!(&*or, "world");
+    }
+
+    #[test]
+    fn builder() {
+        let built: BoxRef<Example, u8> = OwningRef::builder(Box::new(example()))
+            .project(|e| &e.2)
+            .project(|a| &a[1..])
+            .project(|s| &s[1])
+            .build();
+
+        let chained: BoxRef<Example, u8> = BoxRef::new(Box::new(example()))
+            .map(|e| &e.2)
+            .map(|a| &a[1..])
+            .map(|s| &s[1]);
+
+        assert_eq!(*built, *chained);
+        assert_eq!(*built, 3);
+    }
+
+    #[test]
+    fn eq() {
+        let or1: BoxRef<[u8]> = BoxRef::new(vec![1, 2, 3].into_boxed_slice());
+        let or2: BoxRef<[u8]> = BoxRef::new(vec![1, 2, 3].into_boxed_slice());
+        let or3: BoxRef<[u8]> = BoxRef::new(vec![1, 2, 4].into_boxed_slice());
+        assert_eq!(or1, or2);
+        assert!(or1 != or3);
+    }
+
+    #[test]
+    fn cow_owner_eq() {
+        use super::CowRef;
+        use std::borrow::Cow;
+
+        let borrowed: CowRef<str> = CowRef::new(Cow::Borrowed("hello world"));
+        let owned: CowRef<str> = CowRef::new(Cow::Owned("hello world".to_string()));
+        assert_eq!(borrowed, owned);
+
+        let borrowed = borrowed.map(|s| &s[6..]);
+        let owned = owned.map(|s| &s[6..]);
+        assert_eq!(borrowed, owned);
+        assert_eq!(&*owned, "world");
+    }
+
+    #[test]
+    fn cow_owner_moves() {
+        use super::CowRef;
+        use std::borrow::Cow;
+        use std::ffi::{CStr, CString, OsStr, OsString};
+        use std::path::{Path, PathBuf};
+
+        let c: CowRef<CStr> = CowRef::new(Cow::Owned(CString::new("abc").unwrap()));
+        let c = c.map(|c| c.to_bytes());
+        let os: CowRef<OsStr> = CowRef::new(Cow::Owned(OsString::from("abc")));
+        let path: CowRef<Path> = CowRef::new(Cow::Owned(PathBuf::from("a/b.txt")));
+        let path = path.map(|p| p.file_name().unwrap());
+
+        let moved = Box::new((c, os, path));
+        assert_eq!(&*moved.0, b"abc");
+        assert_eq!(&*moved.1, "abc");
+        assert_eq!(&*moved.2, "b.txt");
+    }
+
+    #[test]
+    fn scratch_owner() {
+        let scratch: ScratchOwner<u8> = ScratchOwner::with_capacity(32);
+
+        let or = ScratchOwner::reset_and_project(scratch, |buf| buf.extend(b"GET /index.html"), |s| &s[4..]);
+        assert_eq!(&*or, b"/index.html");
+        let buf = or.owner().as_ptr();
+
+        let or = ScratchOwner::reset_and_project(or.into_inner(), |buf| buf.extend(b"PUT /a"), |s| &s[..3]);
+        assert_eq!(&*or, b"PUT");
+        assert_eq!(or.owner().as_ptr(), buf);
+        assert_eq!(or.owner().len(), 6);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn map_pod() {
+        #[repr(align(4))]
+        struct Aligned([u8; 8]);
+
+        let or = BoxRef::new(Box::new(Aligned([1, 0, 0, 0, 2, 0, 0, 0])));
+        let value = or.map(|a| &a.0[..4]).map_pod::<u32>().unwrap();
+        assert_eq!(*value, u32::from_le_bytes([1, 0, 0, 0]));
+
+        let or = BoxRef::new(Box::new(Aligned([0; 8])));
+        assert!(or.map(|a| &a.0[1..5]).map_pod::<u32>().is_none());
+
+        let or = BoxRef::new(Box::new(Aligned([0; 8])));
+        assert!(or.map(|a| &a.0[..2]).map_pod::<u32>().is_none());
+    }
+
+    #[test]
+    fn detach() {
+        let or: BoxRef<Example, str> = BoxRef::new(Box::new(example())).map(|e| &e.1[..5]);
+        let hello: String = or.detach();
+        drop(or);
+        assert_eq!(hello, "hello");
+
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3, 4]).map(|v| &v[1..3]);
+        let middle: Vec<i32> = or.detach();
+        drop(or);
+        assert_eq!(middle, [2, 3]);
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn verify_stable_address() {
+        use super::StableAddress;
+        use std::ops::Deref;
+
+        struct Handle(Box<Example>);
+
+        impl Deref for Handle {
+            type Target = Example;
+
+            fn deref(&self) -> &Example {
+                &self.0
+            }
+        }
+
+        unsafe impl StableAddress for Handle {}
+
+        let owner = super::verify_stable_address(Handle(Box::new(example())));
+        let or = OwningRef::new(owner).map(|e| &e.1);
+        assert_eq!(&**or, "hello world");
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    #[should_panic(expected = "after being moved")]
+    fn verify_stable_address_inline_owner() {
+        use super::StableAddress;
+        use std::ops::Deref;
+
+        struct Inline(Example);
+
+        impl Deref for Inline {
+            type Target = Example;
+
+            fn deref(&self) -> &Example {
+                &self.0
+            }
+        }
+
+        unsafe impl StableAddress for Inline {}
+
+        super::verify_stable_address(Inline(example()));
+    }
+
+    #[test]
+    fn panics_at_caller() {
+        use std::cell::Cell;
+        use std::panic;
+
+        thread_local!(static PANIC_LINE: Cell<Option<u32>> = const { Cell::new(None) });
+
+        fn panic_line<F: FnOnce() + panic::UnwindSafe>(f: F) -> Option<u32> {
+            PANIC_LINE.with(|l| l.set(None));
+            assert!(panic::catch_unwind(f).is_err());
+            PANIC_LINE.with(|l| l.get())
+        }
+
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            PANIC_LINE.with(|l| l.set(info.location().map(|l| l.line())));
+            prev(info);
+        }));
+
+        let vec_ref = || VecRef::new(vec![1, 2, 3]);
+        let bytes_ref = || BoxRef::new(Box::new([1u8, 2])).map(|a| &a[..]);
+
+        assert_eq!(panic_line(|| { vec_ref().project_owned(3); }), Some(line!()));
+        assert_eq!(panic_line(|| { bytes_ref().read_u32_be(); }), Some(line!()));
+        assert_eq!(panic_line(|| { bytes_ref().map_aligned::<3>(); }), Some(line!()));
+        assert_eq!(panic_line(|| { bytes_ref().range(1..3); }), Some(line!()));
+    }
+
+    #[test]
+    fn collect_refs() {
+        use std::sync::Arc;
+
+        let shared = Arc::new(example());
+        let inner: Vec<ArcRef<Example, u8>> = (0..3)
+            .map(|i| ArcRef::new(shared.clone()).map(|e| &e.2[i]))
+            .collect();
+
+        let outer = super::collect_refs(inner);
+        let values: Vec<u8> = outer.iter().map(|r| **r).collect();
+        assert_eq!(values, [1, 2, 3]);
+
+        let tail = outer.map(|refs| &refs[1..]);
+        assert_eq!(*tail[0], 2);
+        assert_eq!(Arc::strong_count(&shared), 4);
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn zeroizing_owner() {
+        use super::{StableAddress, Zeroizing};
+        use std::ops::{Deref, DerefMut};
+
+        // Borrows the secret, so that it can still be inspected after the drop.
+        struct Borrowed<'a>(&'a mut [u8]);
+
+        impl<'a> Deref for Borrowed<'a> {
+            type Target = [u8];
+
+            fn deref(&self) -> &[u8] {
+                self.0
+            }
+        }
+
+        impl<'a> DerefMut for Borrowed<'a> {
+            fn deref_mut(&mut self) -> &mut [u8] {
+                self.0
+            }
+        }
+
+        unsafe impl<'a> StableAddress for Borrowed<'a> {}
+
+        let mut secret = *b"hunter2";
+        {
+            let or = OwningRef::new(Zeroizing::new(Borrowed(&mut secret))).map(|s| &s[..4]);
+            assert_eq!(&*or, b"hunt");
+        }
+        assert_eq!(secret, [0; 7]);
+    }
+
+    #[test]
+    fn memoized() {
+        use std::cell::Cell;
+
+        let or: BoxRef<Example, str> = BoxRef::new(Box::new(example())).map(|e| &e.1[..]);
+        let mut memo = Memoized::new(or);
+        let runs = Cell::new(0);
+        let word = |s: &str, i: usize| {
+            runs.set(runs.get() + 1);
+            s.split(' ').nth(i).unwrap().len()
+        };
+
+        assert_eq!(memo.get_or_project(0, |s| &s[..word(s, 0)]), "hello");
+        assert_eq!(memo.get_or_project(1, |s| &s[s.len() - word(s, 1)..]), "world");
+        assert_eq!(memo.get_or_project(0, |s| &s[..word(s, 0)]), "hello");
+        assert_eq!(memo.get_or_project(1, |s| &s[s.len() - word(s, 1)..]), "world");
+        assert_eq!(runs.get(), 2);
+
+        assert_eq!(&**memo, "hello world");
+    }
+
+    #[test]
+    fn owner_strong_count() {
+        use std::sync::Arc;
+
+        let or: ArcRef<Example, str> = ArcRef::new(Arc::new(example())).map(|e| &e.1[..]);
+        let clones: Vec<_> = (0..3).map(|i| or.clone().map(move |s| &s[i..])).collect();
+        assert_eq!(or.owner_strong_count(), 4);
+
+        drop(clones);
+        assert_eq!(or.owner_strong_count(), 1);
+
+        let or: RcRef<Example, u32> = RcRef::new(Rc::new(example())).map(|e| &e.0);
+        let clone = or.clone();
+        assert_eq!(clone.owner_strong_count(), 2);
+    }
+
+    #[test]
+    fn map_cow() {
+        use std::borrow::Cow;
+
+        fn normalized(s: &str) -> Cow<'_, str> {
+            if s.chars().all(|c| c.is_lowercase() || c == ' ') {
+                Cow::Borrowed(s.trim())
+            } else {
+                Cow::Owned(s.to_lowercase())
+            }
+        }
+
+        let or: BoxRef<Example, str> = BoxRef::new(Box::new(example())).map(|e| &e.1[..]);
+        match or.map_cow(normalized) {
+            MapCow::Borrowed(or) => assert_eq!(&*or, "hello world"),
+            MapCow::Owned(_) => panic!("expected a borrowed projection"),
+        }
+
+        let or: BoxRef<String, str> = BoxRef::new(Box::new("Hello World".to_string())).map(|s| &s[..]);
+        let cow = or.map_cow(normalized);
+        assert_eq!(&*cow, "hello world");
+        match cow {
+            MapCow::Owned(owned) => assert_eq!(owned, "hello world"),
+            MapCow::Borrowed(_) => panic!("expected an owned value"),
+        }
+    }
+
+    #[test]
+    fn reroot_arc() {
+        use std::sync::Arc;
+
+        struct Outer {
+            inner: Arc<Example>,
+        }
+
+        let outer = Arc::new(Outer { inner: Arc::new(example()) });
+        let or: ArcRef<Outer, Arc<Example>> = ArcRef::new(outer.clone()).map(|o| &o.inner);
+        assert_eq!(Arc::strong_count(&outer), 2);
+
+        let inner: ArcRef<Example> = or.reroot_arc(|inner| inner);
+        assert_eq!(Arc::strong_count(&outer), 1);
+        assert_eq!(Arc::strong_count(&outer.inner), 2);
+
+        let inner = inner.map(|e| &e.1[6..]);
+        drop(outer);
+        assert_eq!(&*inner, "world");
+        assert_eq!(inner.owner_strong_count(), 1);
+    }
+
+    #[test]
+    fn require_static() {
+        use super::StaticOwningRef;
+        use std::any::Any;
+
+        fn store<T: 'static>(registry: &mut Vec<Box<dyn Any>>, value: T) {
+            registry.push(Box::new(value));
+        }
+
+        let mut registry = Vec::new();
+        let or: StaticOwningRef<str> = BoxRef::new(Box::new(example()))
+            .map(|e| &e.1[..5])
+            .erase_owner();
+        store(&mut registry, or.require_static());
+
+        let or = registry[0].downcast_ref::<StaticOwningRef<str>>().unwrap();
+        assert_eq!(&**or, "hello");
+    }
+
+    #[test]
+    fn fields_owned() {
+        use std::fmt::Debug;
+
+        let or = RcRef::new(Rc::new(example()));
+        let fields: Vec<RcRef<Example, dyn Debug>> = fields_owned!(or; 0, 1, 2);
+        drop(or);
+
+        let formatted: Vec<String> = fields.iter().map(|f| format!("{:?}", &**f)).collect();
+        assert_eq!(formatted, ["42", "\"hello world\"", "[1, 2, 3]"]);
+        assert_eq!(Rc::strong_count(fields[0].owner()), 3);
+    }
+
+    #[test]
+    fn valid_utf8_prefix() {
+        use std::sync::Arc;
+
+        let mut bytes = "grüße".as_bytes().to_vec();
+        bytes.extend_from_slice(&"€".as_bytes()[..2]);
+
+        let or: ArcRef<Vec<u8>, [u8]> = ArcRef::new(Arc::new(bytes)).map(|v| &v[..]);
+        assert_eq!(&*or.clone().valid_utf8_prefix(), "grüße");
+
+        let complete = or.map(|s| &s[..s.len() - 2]).valid_utf8_prefix();
+        assert_eq!(&*complete, "grüße");
+    }
+
+    #[test]
+    fn modify() {
+        let or: BoxRef<Example, str> = BoxRef::new(Box::new(example())).map(|e| &e.1[..5]);
+        assert_eq!(&*or, "hello");
+
+        let (or, old) = or.modify(|e| {
+            e.1.push_str(", goodbye world");
+            e.0 += 1;
+            e.0 - 1
+        });
+        assert_eq!(old, 42);
+        assert_eq!(or.0, 43);
+
+        let or = or.map(|e| &e.1[13..]);
+        assert_eq!(&*or, "goodbye world");
+    }
+
+    #[cfg(feature = "spin")]
+    #[test]
+    fn spin_guards() {
+        use super::{SpinMutexGuardRef, SpinMutexGuardRefMut, SpinRwLockReadGuardRef, SpinRwLockWriteGuardRefMut};
+
+        let mutex = spin::Mutex::new(example());
+        {
+            let mut or: SpinMutexGuardRefMut<Example, u32> = OwningRefMut::new(mutex.lock())
+                .map_mut(|e| &mut e.0);
+            *or += 1;
+            assert!(mutex.try_lock().is_none());
+        }
+        let or: SpinMutexGuardRef<Example, str> = OwningRef::new(mutex.lock()).map(|e| &e.1[..5]);
+        assert_eq!(&*or, "hello");
+        assert_eq!(or.owner().0, 43);
+
+        let lock = spin::RwLock::new(example());
+        {
+            let mut or: SpinRwLockWriteGuardRefMut<Example, [u8]> = OwningRefMut::new(lock.write())
+                .map_mut(|e| &mut e.2[1..]);
+            or[0] = 0;
+        }
+        let or1: SpinRwLockReadGuardRef<Example, [u8]> = OwningRef::new(lock.read()).map(|e| &e.2[..]);
+        let or2: SpinRwLockReadGuardRef<Example, u8> = OwningRef::new(lock.read()).map(|e| &e.2[1]);
+        assert_eq!(&*or1, [1, 0, 3]);
+        assert_eq!(*or2, 0);
+    }
+
+    #[test]
+    fn non_empty() {
+        use super::StringRef;
+
+        let or = StringRef::new("hello world".to_string()).map(|s| &s[6..]);
+        assert_eq!(&*or.non_empty().unwrap(), "world");
+
+        let or = StringRef::new("hello world".to_string()).map(|s| &s[11..]);
+        assert_eq!(or.non_empty().unwrap_err(), "hello world");
+
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2]).map(|v| &v[2..]);
+        assert_eq!(or.non_empty().unwrap_err(), [1, 2]);
+    }
+
+    #[cfg(feature = "bytemuck")]
+    #[test]
+    fn as_raw_bytes() {
+        let or: BoxRef<Example, u32> = BoxRef::new(Box::new(example())).map(|e| &e.0);
+        assert_eq!(or.as_raw_bytes(), 42u32.to_ne_bytes());
+
+        let or: BoxRef<Example, [u8; 3]> = BoxRef::new(Box::new(example())).map(|e| &e.2);
+        assert_eq!(or.as_raw_bytes(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn iter_owned_slice() {
+        use std::sync::Arc;
+
+        let or: ArcRef<Vec<i32>, [i32]> = ArcRef::new(Arc::new(vec![1, 2, 3, 4, 5])).map(|v| &v[1..]);
+
+        let forward: Vec<i32> = or.clone().iter_owned().map(|r| *r).collect();
+        assert_eq!(forward, [2, 3, 4, 5]);
+
+        let mut iter = or.clone().iter_owned_rev();
+        assert_eq!(*iter.next().unwrap(), 5);
+        assert_eq!(*iter.next_back().unwrap(), 2);
+        let rest: Vec<ArcRef<Vec<i32>, i32>> = iter.collect();
+        assert_eq!(*rest[0], 4);
+        assert_eq!(*rest[1], 3);
+        assert_eq!(or.owner_strong_count(), 3);
+    }
+
+    #[test]
+    fn owner_ptr() {
+        let or: RcRef<Example, str> = RcRef::new(Rc::new(example())).map(|e| &e.1[..]);
+        assert_eq!(or.owner_ptr(), &**or.owner() as *const Example);
+        assert_eq!(or.clone().map(|s| &s[6..]).owner_ptr(), or.owner_ptr());
+
+        assert!(OwningRef::ptr_eq(&or, &or.clone()));
+        assert!(!OwningRef::ptr_eq(&or, &or.clone().map(|s| &s[..5])));
+    }
+
+    #[test]
+    #[cfg(not(all(feature = "debug-locations", debug_assertions)))]
+    fn size_of() {
+        use std::mem::size_of;
+
+        // The owner pointer is not cached, keeping owning references small.
+        assert_eq!(size_of::<BoxRef<Example, u32>>(), 2 * size_of::<usize>());
+        assert_eq!(size_of::<RcRef<Example, str>>(), 3 * size_of::<usize>());
+    }
+
+    #[test]
+    fn owning_ref_cursor() {
+        use super::OwningRefCursor;
+        use std::io::Read;
+
+        let or: BoxRef<Example, [u8]> = BoxRef::new(Box::new(example())).map(|e| e.1.as_bytes());
+        let mut cursor = OwningRefCursor::new(or);
+
+        let mut buf = [0; 6];
+        assert_eq!(cursor.read(&mut buf).unwrap(), 6);
+        assert_eq!(&buf, b"hello ");
+
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"world");
+        assert_eq!(cursor.position(), 11);
+        assert_eq!(cursor.read(&mut buf).unwrap(), 0);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn owning_ref_cursor_buf() {
+        use super::OwningRefCursor;
+        use bytes::Buf;
+        use std::sync::Arc;
+
+        let or: ArcRef<Vec<u8>, [u8]> = ArcRef::new(Arc::new(vec![0, 0, 0, 42, 0, 7, 9]))
+            .map(|v| &v[..]);
+        let mut cursor = OwningRefCursor::new(or);
+
+        assert_eq!(cursor.get_u32(), 42);
+        assert_eq!(cursor.get_u16(), 7);
+        assert_eq!(cursor.remaining(), 1);
+        assert_eq!(cursor.chunk(), [9]);
+    }
+
+    #[test]
+    fn map_split_prefix() {
+        use std::sync::Arc;
+
+        let or: ArcRef<Vec<u8>, [u8]> = ArcRef::new(Arc::new(vec![2, b'h', b'i', 1, b'!']))
+            .map(|v| &v[..]);
+
+        let mut messages = Vec::new();
+        let mut rest = or;
+        while !rest.is_empty() {
+            let (len, tail) = rest.map_split_prefix(1);
+            let (message, tail) = tail.map_split_prefix(len[0] as usize);
+            messages.push(message);
+            rest = tail;
+        }
+
+        assert_eq!(&*messages[0], b"hi");
+        assert_eq!(&*messages[1], b"!");
+        assert_eq!(rest.owner_strong_count(), 3);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn arc_to_rc() {
+        let or: ArcRef<[i32]> = super::arc_ref_from_vec(vec![1, 2, 3, 4]);
+        let narrowed = or.clone().map(|s| &s[1..3]);
+
+        let demoted: RcRef<[i32], [i32]> = narrowed.arc_to_rc();
+        assert_eq!(&*demoted, &[2, 3]);
+        assert_eq!(&**demoted.owner(), &[1, 2, 3, 4]);
+        assert!(!std::ptr::eq(demoted.owner().as_ptr(), or.as_ptr()));
+
+        let elem: RcRef<[i32], i32> = or.map(|s| &s[3]).arc_to_rc();
+        assert_eq!(*elem, 4);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    #[should_panic(expected = "referent does not lie within the shared slice")]
+    fn arc_to_rc_heap_projection() {
+        use std::sync::Arc;
+
+        let or: ArcRef<[String]> = ArcRef::new(Arc::from(vec!["hello".to_string()]));
+        let first: ArcRef<[String], str> = or.map(|s| &s[0][..]);
+        let _ = first.arc_to_rc();
+    }
+
+    #[test]
+    fn box_ref_into_arc_ref() {
+        use std::convert::TryFrom;
+        use std::sync::Arc;
+
+        // Generic code using `TryFrom` picks up the conversion as well.
+        fn promote<T, U: TryFrom<T>>(t: T) -> Result<U, U::Error> {
+            U::try_from(t)
+        }
+
+        let or: BoxRef<Example, u32> = BoxRef::new(Box::new(example())).map(|e| &e.0);
+        let or: ArcRef<Example, u32> = promote(or).unwrap();
+        assert_eq!(*or, 42);
+        assert!(std::ptr::eq(&*or, &or.owner().0));
+
+        let clone = or.clone();
+        assert_eq!(*clone, 42);
+        assert_eq!(Arc::strong_count(or.owner()), 2);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn par_map_each() {
+        use std::sync::Arc;
+
+        let values: Vec<Example> = (0..100).map(|i| Example(i, i.to_string(), [0; 3])).collect();
+        let or: ArcRef<Vec<Example>, [Example]> = ArcRef::new(Arc::new(values)).map(|v| &v[..]);
+
+        let numbers: Vec<ArcRef<Vec<Example>, u32>> = or.clone().par_map_each(|e| &e.0);
+        assert_eq!(numbers.iter().map(|n| **n).sum::<u32>(), 4950);
+        assert_eq!(*numbers[99], 99);
+        assert_eq!(or.owner_strong_count(), 101);
+    }
+
+    #[test]
+    fn on_expire() {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let expirations = Arc::new(AtomicUsize::new(0));
+        let counter = expirations.clone();
+
+        let or = BoxRef::new(Box::new(example()))
+            .map(|e| &e.1[6..])
+            .on_expire(move || { counter.fetch_add(1, Ordering::SeqCst); });
+        let clones = vec![or.clone(), or.clone()];
+        let weak = or.downgrade();
+
+        drop(or);
+        assert_eq!(&*weak.upgrade().unwrap(), "world");
+        let last = ::std::thread::spawn(move || {
+            let len = clones.iter().map(|c| c.len()).sum::<usize>();
+            drop(clones);
+            len
+        });
+        assert_eq!(last.join().unwrap(), 10);
+
+        assert_eq!(expirations.load(Ordering::SeqCst), 1);
+        assert!(weak.is_expired());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn ord() {
+        let or1: BoxRef<[u8]> = BoxRef::new(vec![1, 2, 3].into_boxed_slice());
+        let or2: BoxRef<[u8]> = BoxRef::new(vec![1, 2, 4].into_boxed_slice());
+        assert!(or1 < or2);
+        assert_eq!(or1.cmp(&or2), ::std::cmp::Ordering::Less);
+    }
+
+    #[test]
+    fn btree_map_keys() {
+        use super::StringRef;
+        use std::collections::BTreeMap;
+
+        fn erased<O: 'static>(or: OwningRef<O, str>) -> ErasedBoxRef<str> {
+            BoxRef::new(Box::new(or)).map(|or| &**or).erase_owner()
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert(erased(StringRef::new("pear".to_string())), 1);
+        map.insert(erased(RcRef::new(Rc::new(example())).map(|e| &e.1[6..])), 2);
+        map.insert(erased(StringRef::new("xapple".to_string()).map(|s| &s[1..])), 3);
+        map.insert(erased(RcRef::new(Rc::new("pear".to_string())).map(|s| &s[..])), 4);
+
+        let entries: Vec<(&str, i32)> = map.iter().map(|(k, v)| (&**k, *v)).collect();
+        assert_eq!(entries, [("apple", 3), ("pear", 4), ("world", 2)]);
+    }
+
+    #[test]
+    fn reference_owner() {
+        static EXAMPLE: &str = "hello world";
+
+        let or: OwningRef<&'static str, str> = OwningRef::new(EXAMPLE).map(|s| &s[6..]);
+        let clone = or.clone();
+
+        let local = "world".to_string();
+        let shortened = {
+            let local_ref: &str = &local;
+            let mut refs: Vec<OwningRef<&str, str>> = vec![OwningRef::new(local_ref)];
+            refs.push(or.shorten_owner());
+            refs
+        };
+        assert_eq!(shortened[0], shortened[1]);
+        assert_eq!(&*clone, "world");
+    }
+
+    #[test]
+    fn static_owner() {
+        use super::StaticOwner;
+
+        static NUMBERS: [u32; 3] = [1, 2, 3];
+
+        let constant: OwningRef<StaticOwner, [u32]> = OwningRef::constant(&NUMBERS[..]);
+        let clone = constant.clone().map(|s| &s[1..]);
+        assert_eq!(&*clone, &[2, 3]);
+
+        let owned: BoxRef<Example, [u32]> = BoxRef::new(Box::new(example())).map(|e| ::std::slice::from_ref(&e.0));
+        let refs: Vec<ErasedBoxRef<[u32]>> = vec![constant.erase_owner(), owned.erase_owner(), clone.erase_owner()];
+
+        let lens: Vec<usize> = refs.iter().map(|r| r.len()).collect();
+        assert_eq!(lens, [3, 1, 2]);
+        assert_eq!(refs[1][0], 42);
+    }
+
+    #[test]
+    fn reproject() {
+        use std::sync::Arc;
+
+        let values = Arc::new(vec![10, 20, 30]);
+        let mut cursor: ArcRef<Vec<i32>, i32> = ArcRef::new(values.clone()).map(|v| &v[0]);
+
+        let mut seen = vec![*cursor];
+        for i in 1..values.len() {
+            cursor.reproject(|v, _| &v[i]);
+            seen.push(*cursor);
+        }
+        assert_eq!(seen, [10, 20, 30]);
+        assert!(std::ptr::eq(&*cursor, &values[2]));
+
+        cursor.reproject(|v, cur| if *cur > 20 { &v[0] } else { cur });
+        assert_eq!(*cursor, 10);
+    }
+
+    mod custom_owner {
+        use super::super::{OwningRef, StableAddress, CloneStableAddress};
+        use std::ops::Deref;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Inner<T> {
+            count: AtomicUsize,
+            value: T,
+        }
+
+        /// A minimal reference counted pointer standing in for crates like
+        /// `triomphe` or `servo_arc`.
+        pub struct MyArc<T>(*mut Inner<T>);
+
+        impl<T> MyArc<T> {
+            pub fn new(value: T) -> MyArc<T> {
+                let inner = Box::new(Inner { count: AtomicUsize::new(1), value });
+                MyArc(Box::into_raw(inner))
+            }
+
+            pub fn count(this: &MyArc<T>) -> usize {
+                unsafe { (*this.0).count.load(Ordering::SeqCst) }
+            }
+        }
+
+        impl<T> Clone for MyArc<T> {
+            fn clone(&self) -> MyArc<T> {
+                unsafe { (*self.0).count.fetch_add(1, Ordering::SeqCst); }
+                MyArc(self.0)
+            }
+        }
+
+        impl<T> Drop for MyArc<T> {
+            fn drop(&mut self) {
+                unsafe {
+                    if (*self.0).count.fetch_sub(1, Ordering::SeqCst) == 1 {
+                        drop(Box::from_raw(self.0));
+                    }
+                }
+            }
+        }
+
+        impl<T> Deref for MyArc<T> {
+            type Target = T;
+            fn deref(&self) -> &T {
+                unsafe { &(*self.0).value }
+            }
+        }
+
+        unsafe impl<T> StableAddress for MyArc<T> {}
+        unsafe impl<T> CloneStableAddress for MyArc<T> {}
+
+        #[test]
+        fn project() {
+            let or = OwningRef::new(MyArc::new(vec![1, 2, 3])).map(|v| &v[1]);
+            assert_eq!(*or, 2);
+            assert_eq!(or.owner()[2], 3);
+        }
+
+        #[test]
+        fn clone_shares_allocation() {
+            let or = OwningRef::new(MyArc::new((String::from("a"), 5))).map(|t| &t.1);
+            let other = or.clone();
+            assert_eq!(MyArc::count(or.owner()), 2);
+            assert!(::std::ptr::eq(&*or, &*other));
+
+            drop(or);
+            assert_eq!(MyArc::count(other.owner()), 1);
+            assert_eq!(*other, 5);
+        }
+    }
+
+    #[test]
+    fn send_sync_interior_mutability() {
+        use std::cell::Cell;
+        use std::sync::{Arc, Mutex};
+
+        fn assert_send<T: Send>(_: &T) {}
+        fn assert_sync<T: Sync>(_: &T) {}
+
+        let or: BoxRef<(Cell<i32>, i32), i32> =
+            BoxRef::new(Box::new((Cell::new(1), 2))).map(|t| &t.1);
+        assert_send(&or);
+
+        let or: ArcRef<Mutex<Cell<i32>>> = ArcRef::new(Arc::new(Mutex::new(Cell::new(1))));
+        assert_send(&or);
+        assert_sync(&or);
+    }
+
+    #[test]
+    fn try_map_result() {
+        let ok = || BoxRef::new(Box::new((1, Ok::<String, ()>("yes".to_string())))).map(|t| &t.1);
+        let err = || BoxRef::new(Box::new((2, Err::<String, ()>(())))).map(|t| &t.1);
+
+        assert_eq!(&*ok().try_map_ok().unwrap(), "yes");
+        assert_eq!(ok().try_map_err().unwrap_err().0, 1);
+
+        assert_eq!(err().try_map_ok().unwrap_err().0, 2);
+        assert_eq!(*err().try_map_err().unwrap(), ());
+    }
+
+    #[test]
+    fn range() {
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3, 4, 5]);
+        let sub = or.range(2..4);
+        assert_eq!(&*sub, &[3, 4]);
+        assert!(::std::ptr::eq(&sub[0], &sub.owner()[2]));
+
+        let empty = sub.range(2..2);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn range_out_of_bounds() {
+        let or = VecRef::new(vec![1, 2, 3]);
+        or.range(2..4);
+    }
+
+    #[test]
+    fn map_blocking() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let data = Arc::new(example());
+        let shared = data.clone();
+        let or: ArcRef<Example, str> = thread::spawn(move || {
+            ArcRef::new(shared).map_blocking(|e| &e.1[..])
+        }).join().unwrap();
+
+        assert_eq!(&*or, "hello world");
+        assert!(::std::ptr::eq(&*or, &data.1[..]));
+    }
+
+    #[test]
+    fn make_mut_owner() {
+        use std::sync::Arc;
+
+        // shared: the target is cloned
+        let shared = Arc::new(vec![1, 2, 3]);
+        let or: ArcRef<Vec<i32>, i32> = ArcRef::new(shared.clone()).map(|v| &v[0]);
+        let mut or = or.make_mut_owner();
+        or.push(4);
+        assert_eq!(*or, [1, 2, 3, 4]);
+        assert_eq!(*shared, [1, 2, 3]);
+        assert_eq!(Arc::strong_count(&shared), 1);
+
+        // unique: the target is mutated in place
+        let or: ArcRef<Vec<i32>, i32> = ArcRef::new(Arc::new(vec![5, 6])).map(|v| &v[1]);
+        let before: *const Vec<i32> = &**or.owner();
+        let mut or = or.make_mut_owner();
+        or[0] = 50;
+        assert!(::std::ptr::eq(before, &*or));
+
+        let arc = or.into_inner().into_arc();
+        assert_eq!(*arc, [50, 6]);
+    }
+
+    #[test]
+    fn flatten_option() {
+        let or = || BoxRef::new(Box::new((Some("a".to_string()), None::<String>)));
+
+        let some = or().map(|t| &t.0).flatten_option().unwrap();
+        assert_eq!(&*some, "a");
+
+        let owner = or().map(|t| &t.1).flatten_option().unwrap_err();
+        assert_eq!(owner.0.as_ref().unwrap(), "a");
+    }
+
+    #[test]
+    fn downcast_ref_owned() {
+        use std::any::Any;
+
+        let values: Vec<Box<dyn Any>> = vec![Box::new(7u32), Box::new("seven")];
+        let or = OwningRef::new(values).map(|v| &*v[0]);
+
+        let or = or.downcast_ref_owned::<String>().unwrap_err();
+        let or = or.downcast_ref_owned::<u32>().unwrap();
+        assert_eq!(*or, 7);
+        assert_eq!(or.owner().len(), 2);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn arc_ref_from_iter() {
+        let or: ArcRef<[i32]> = super::arc_ref_from_iter(0..5);
+        assert_eq!(&*or, &[0, 1, 2, 3, 4]);
+
+        let sub = or.clone().map(|s| &s[1..3]);
+        assert_eq!(&*sub, &[1, 2]);
+
+        let filtered: ArcRef<[i32]> = super::arc_ref_from_iter((0..5).filter(|i| i % 2 == 0));
+        assert_eq!(&*filtered, &[0, 2, 4]);
+    }
+
+    #[test]
+    fn with_owner() {
+        use std::sync::Arc;
+
+        let or = ArcRef::new(Arc::new(example())).map(|e| &e.1);
+        let other = or.clone();
+
+        assert_eq!(or.with_owner(Arc::strong_count), 2);
+        let len = other.with_owner(|o| o.1.len() + o.2.len());
+        assert_eq!(len, 14);
+
+        drop(other);
+        assert_eq!(or.with_owner(Arc::strong_count), 1);
+    }
+
+    #[test]
+    fn map_boxed() {
+        use std::rc::Rc;
+
+        let words = Rc::new(vec!["a".to_string(), "bc".to_string()]);
+        let or = RcRef::new(words.clone()).map_boxed(|w| w.concat().into_boxed_str());
+
+        assert_eq!(&*or, "abc");
+        assert_eq!(Rc::strong_count(&words), 2);
+        assert_eq!(&*or.owner().1, "abc");
+
+        drop(or);
+        assert_eq!(Rc::strong_count(&words), 1);
+    }
+
+    #[test]
+    fn just_owner() {
+        use std::rc::Rc;
+        use super::JustOwner;
+
+        let shared = Rc::new(example());
+        let kept: Vec<JustOwner<RcRef<Example, str>>> = (0..3)
+            .map(|_| JustOwner::new(RcRef::new(shared.clone()).map(|e| &e.1[..])))
+            .collect();
+        assert_eq!(Rc::strong_count(&shared), 4);
+
+        drop(kept);
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn map_with_owner_ptr() {
+        let data = Box::new(example());
+        let base: *const Example = &*data;
+
+        let (or, ptr) = BoxRef::new(data).map_with_owner_ptr(|e| &e.2);
+        assert_eq!(ptr, base);
+
+        let (or, ptr2) = or.map_with_owner_ptr(|a| &a[1]);
+        assert_eq!(*or, 2);
+        assert_eq!(ptr2, base);
+        assert!(::std::ptr::eq(&*or, unsafe { &(*ptr2).2[1] }));
+    }
+
+    #[test]
+    fn read_arc() {
+        use std::sync::{Arc, RwLock};
+        use std::thread;
+
+        let arc = Arc::new(RwLock::new(example()));
+        let handle = super::read_arc(arc.clone());
+        let text: &str = &handle.1;
+        assert_eq!(text, "hello world");
+
+        // more readers are let in while `handle` is alive
+        let other = super::read_arc(arc.clone());
+        assert_eq!(other.0, 42);
+        assert!(arc.try_write().is_err());
+
+        let shared = &handle;
+        let sum: u32 = thread::scope(|s| {
+            let readers: Vec<_> = (0..3)
+                .map(|i| s.spawn(move || shared.2[i] as u32))
+                .collect();
+            readers.into_iter().map(|r| r.join().unwrap()).sum()
+        });
+        assert_eq!(sum, 6);
+
+        drop(handle);
+        drop(other);
+        assert!(arc.try_write().is_ok());
+    }
+
+    #[test]
+    fn write_arc() {
+        use std::sync::{Arc, RwLock};
+
+        let arc = Arc::new(RwLock::new(example()));
+        let mut handle = super::write_arc(arc.clone());
+        {
+            let field: &mut String = &mut handle.1;
+            field.push('!');
+        }
+        handle.2[0] = 10;
+        assert!(arc.try_read().is_err());
+        drop(handle);
+
+        let reader = super::read_arc(arc);
+        assert_eq!(reader.1, "hello world!");
+        assert_eq!(reader.2, [10, 2, 3]);
+    }
+
+    #[test]
+    fn try_into_array() {
+        use std::sync::Arc;
+
+        let data = Arc::new(vec![1u8, 2, 3, 4, 5]);
+        let header: ArcRef<Vec<u8>, [u8]> = ArcRef::new(data.clone()).map(|v| &v[1..4]);
+        let header = header.try_into_array::<3>().unwrap();
+        assert_eq!(*header, [2, 3, 4]);
+        assert!(::std::ptr::eq(&header[0], &data[1]));
+
+        let short: ArcRef<Vec<u8>, [u8]> = ArcRef::new(data).map(|v| &v[..2]);
+        let short = short.try_into_array::<3>().unwrap_err();
+        assert_eq!(&*short, &[1, 2]);
+    }
+
+    #[cfg(feature = "debug-locations")]
+    #[test]
+    fn last_map_location() {
+        let or = BoxRef::new(Box::new(example()));
+        assert!(or.last_map_location().is_none());
+
+        let or = or.map(|e| &e.2[..]); let line = line!();
+        let or = or.range(1..3);
+
+        let boxed = BoxRef::new(Box::new(example()));
+        let boxed = boxed.map_boxed(|e| Box::new(e.0 + 1)); let boxed_line = line!();
+
+        let shared: RcRef<Vec<u8>, [u8]> = RcRef::new(Rc::new(vec![1, 2, 3])).map(|v| &v[..]);
+        let second = shared.iter_owned().nth(1).unwrap(); let iter_line = line!();
+
+        if cfg!(debug_assertions) {
+            let location = or.last_map_location().unwrap();
+            assert_eq!(location.file(), file!());
+            assert_eq!(location.line(), line);
+            assert_eq!(boxed.last_map_location().unwrap().line(), boxed_line);
+            assert_eq!(second.last_map_location().unwrap().line(), iter_line);
+        } else {
+            assert!(or.last_map_location().is_none());
+            assert!(boxed.last_map_location().is_none());
+        }
+        assert_eq!(*boxed, 43);
+        assert_eq!(*second, 2);
+    }
+
+    #[test]
+    fn get() {
+        struct Clashing(u32);
+
+        impl Clashing {
+            fn get(&self) -> u32 { self.0 * 2 }
+            fn map(&self) -> u32 { self.0 + 1 }
+        }
+
+        fn generic<O, T: ?Sized>(or: &OwningRef<O, T>) -> &T {
+            OwningRef::get(or)
+        }
+
+        let or = BoxRef::new(Box::new((0, Clashing(21)))).map(|t| &t.1);
+        // `get` does not shadow the referent's method...
+        assert_eq!(or.get(), 42);
+        // ...and reaches the referent where the inherent `map` does shadow it.
+        assert_eq!(OwningRef::get(&or).map(), 22);
+        assert_eq!(OwningRef::get(&or).0, 21);
+        assert_eq!(generic(&or).0, 21);
+    }
+
+    #[test]
+    fn new_init() {
+        let or: BoxRef<[u8; 4]> = OwningRef::new_init(Box::new([0u8; 4]), |buf| {
+            for (i, b) in buf.iter_mut().enumerate() {
+                *b = i as u8 * 2;
+            }
+        });
+        assert_eq!(*or, [0, 2, 4, 6]);
+
+        let tail = or.map(|b| &b[2..]);
+        assert_eq!(&*tail, &[4, 6]);
+    }
+
+    #[test]
+    fn iter_owned_exact_size() {
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        let or = RcRef::new(Rc::new([1, 2, 3, 4])).map(|a| &a[..]);
+        let mut iter = or.clone().iter_owned();
+        assert_eq!(iter.len(), 4);
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 2);
+
+        let mut refs = Vec::with_capacity(or.len());
+        let capacity = refs.capacity();
+        refs.extend(or.clone().iter_owned_rev());
+        assert_eq!(refs.capacity(), capacity);
+        assert_eq!(refs.iter().map(|r| **r).collect::<Vec<_>>(), [4, 3, 2, 1]);
+
+        let map: HashMap<_, _> = (0..3).map(|i| (i, i * 10)).collect();
+        assert_eq!(RcRef::new(Rc::new(map)).iter_owned().len(), 3);
+    }
+
+    #[test]
+    fn map_with_cache() {
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        let words = Rc::new(vec!["fig", "apple", "cherry"]);
+        let or = RcRef::new(words.clone()).with_cache();
+        assert!(or.owner().cache().is_none());
+
+        let or = or.map_with_cache(
+            |w| w.iter().map(|s| (s.len(), s.to_uppercase())).collect::<HashMap<_, _>>(),
+            |c| &c[&6][..]);
+
+        // moving the owning reference keeps the cache in place
+        let moved = vec![or];
+        assert_eq!(&*moved[0], "CHERRY");
+        assert_eq!(moved[0].owner().cache().unwrap().len(), 3);
+        assert_eq!(moved[0].owner()[1], "apple");
+        assert_eq!(Rc::strong_count(&words), 2);
+
+        // later projections reuse the cache instead of building it again
+        let or = moved.into_iter().next().unwrap();
+        let or = or.map_with_cache(|_| unreachable!(), |c| &c[&3][..]);
+        assert_eq!(&*or, "FIG");
+
+        drop(or);
+        assert_eq!(Rc::strong_count(&words), 1);
+    }
+
+    #[test]
+    fn try_unwrap_to_box() {
+        use std::sync::Arc;
+
+        let or = ArcRef::new(Arc::new(example())).map(|e| &e.2[1]);
+        let other = or.clone();
+
+        // shared: nothing changes
+        let or = or.try_unwrap_to_box().err().unwrap();
+        assert!(OwningRef::ptr_eq(&or, &other));
+        drop(other);
+
+        // unique: the projection follows the target into the box
+        let or: BoxRef<Example, u8> = or.try_unwrap_to_box().ok().unwrap();
+        assert_eq!(*or, 2);
+        assert!(::std::ptr::eq(&*or, &or.owner().2[1]));
+
+        // projections outside the target are left alone
+        let or = ArcRef::new(Arc::new(example())).map(|e| &e.1[..5]);
+        let or = or.try_unwrap_to_box().ok().unwrap();
+        assert_eq!(&*or, "hello");
+    }
+
+    #[test]
+    fn checked_projections() {
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        let vec_ref = || VecRef::new(vec![1, 2, 3, 4]);
+
+        assert_eq!(*vec_ref().checked_project_owned(1).unwrap(), 2);
+        assert_eq!(&*vec_ref().checked_project_owned(1..3).unwrap(), &[2, 3]);
+        assert_eq!(vec_ref().checked_project_owned(4).unwrap_err(), [1, 2, 3, 4]);
 
-unsafe impl<T: ?Sized> StableAddress for Box<T> {}
-unsafe impl<T> StableAddress for Vec<T> {}
-unsafe impl StableAddress for String {}
-#[cfg(feature = "nightly")]
-unsafe impl<T: ?Sized> StableAddress for Rc<T> {}
-#[cfg(feature = "nightly")]
-unsafe impl<T: ?Sized> StableAddress for Arc<T> {}
-#[cfg(feature = "nightly")]
-unsafe impl<T: ?Sized> CloneStableAddress for Rc<T> {}
-#[cfg(feature = "nightly")]
-unsafe impl<T: ?Sized> CloneStableAddress for Arc<T> {}
+        let map: HashMap<String, i32> = vec![("a".to_string(), 1)].into_iter().collect();
+        let map_ref = RcRef::new(Rc::new(map));
+        let owner = map_ref.clone().checked_project_owned("b").unwrap_err();
+        assert_eq!(owner.len(), 1);
+        assert_eq!(*map_ref.checked_project_owned("a").unwrap(), 1);
 
-#[cfg(not(feature = "nightly"))]
-unsafe impl<T> CloneStableAddress for Rc<T> {}
-#[cfg(not(feature = "nightly"))]
-unsafe impl<T> CloneStableAddress for Arc<T> {}
-#[cfg(not(feature = "nightly"))]
-unsafe impl<T> StableAddress for Rc<T> {}
-#[cfg(not(feature = "nightly"))]
-unsafe impl<T> StableAddress for Arc<T> {}
+        let slice_ref = || RcRef::new(Rc::new([1, 2, 3])).map(|a| &a[..]);
+        let (head, tail) = slice_ref().checked_split_prefix(3).unwrap();
+        assert_eq!((head.len(), tail.len()), (3, 0));
+        assert_eq!(*slice_ref().checked_split_prefix(4).unwrap_err(), [1, 2, 3]);
 
-/// Typedef of a owning reference that uses a `Box` as the owner.
-pub type BoxRef<T, U = T> = OwningRef<Box<T>, U>;
-/// Typedef of a owning reference that uses a `Vec` as the owner.
-pub type VecRef<T, U = T> = OwningRef<Vec<T>, U>;
-/// Typedef of a owning reference that uses a `String` as the owner.
-pub type StringRef = OwningRef<String, str>;
-/// Typedef of a owning reference that uses a `Rc` as the owner.
-pub type RcRef<T, U = T> = OwningRef<Rc<T>, U>;
-/// Typedef of a owning reference that uses a `Arc` as the owner.
-pub type ArcRef<T, U = T> = OwningRef<Arc<T>, U>;
+        assert_eq!(&*vec_ref().checked_range(1..4).unwrap(), &[2, 3, 4]);
+        assert_eq!(vec_ref().checked_range(3..5).unwrap_err(), [1, 2, 3, 4]);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = vec_ref().checked_range(3..2);
+        assert!(reversed.is_err());
+    }
 
-unsafe impl<'a, T: 'a> IntoErased for Box<T> {
-    type Erased = Box<Erased + 'a>;
-    fn into_erased(self) -> Self::Erased { self }
-}
-#[cfg(feature = "nightly")]
-unsafe impl<'a, T: 'a> IntoErased for Rc<T> {
-    type Erased = Rc<Erased + 'a>;
-    fn into_erased(self) -> Self::Erased { self }
-}
-#[cfg(feature = "nightly")]
-unsafe impl<'a, T: 'a> IntoErased for Arc<T> {
-    type Erased = Arc<Erased + 'a>;
-    fn into_erased(self) -> Self::Erased { self }
-}
+    #[test]
+    fn from_conversions() {
+        let or = BoxRef::new(Box::new(example())).map(|e| &e.2[2]);
+        let or: RcRef<Example, u8> = or.into();
+        assert_eq!(*or, 3);
+        assert!(::std::ptr::eq(&*or, &or.owner().2[2]));
 
-/// Typedef of a owning reference that uses an erased `Box` as the owner.
-pub type ErasedBoxRef<U> = OwningRef<Box<Erased>, U>;
-/// Typedef of a owning reference that uses an erased `Rc` as the owner.
-#[cfg(feature = "nightly")]
-pub type ErasedRcRef<U> = OwningRef<Rc<Erased>, U>;
-/// Typedef of a owning reference that uses an erased `Arc` as the owner.
-#[cfg(feature = "nightly")]
-pub type ErasedArcRef<U> = OwningRef<Arc<Erased>, U>;
+        let or = BoxRef::new(Box::new(example())).map(|e| &e.0);
+        let or: ArcRef<Example, u32> = or.into();
+        assert_eq!(*or, 42);
+        assert!(::std::ptr::eq(&*or, &or.owner().0));
 
-#[cfg(test)]
-mod tests {
-    use super::{OwningRef, BoxRef, Erased, ErasedBoxRef};
+        let or: VecRef<i32, [i32]> = VecRef::new(vec![1, 2, 3]).map(|v| &v[1..]);
+        let or: BoxRef<[i32]> = or.into();
+        assert_eq!(&*or, &[2, 3]);
+        let or: VecRef<i32, [i32]> = or.into();
+        assert_eq!(&*or, &[2, 3]);
+        assert_eq!(or.owner().len(), 3);
+    }
 
-    #[derive(Debug, PartialEq)]
-    struct Example(u32, String, [u8; 3]);
-    fn example() -> Example {
-        Example(42, "hello world".to_string(), [1, 2, 3])
+    #[test]
+    fn par_chunks_mut() {
+        let mut or: OwningRefMut<Box<[i32]>, [i32]> =
+            OwningRefMut::new((1..=10).collect::<Vec<_>>().into_boxed_slice());
+
+        let lens = or.par_chunks_mut(3, |chunk| {
+            for x in chunk.iter_mut() {
+                *x *= 2;
+            }
+            chunk.len()
+        });
+
+        assert_eq!(lens, [3, 3, 3, 1]);
+        assert_eq!(&*or, &[2, 4, 6, 8, 10, 12, 14, 16, 18, 20]);
     }
 
     #[test]
-    fn new_deref() {
-        let or: OwningRef<Box<()>, ()> = OwningRef::new(Box::new(()));
-        assert_eq!(&*or, &());
+    fn map_const_promoted() {
+        use std::rc::Rc;
+
+        let owner = Rc::new(());
+        let or: RcRef<(), [u8]> = RcRef::new(owner.clone()).map::<_, [u8]>(|_| &[1, 2, 3]);
+
+        // the literal is promoted, so the reference survives moves and the owner is kept
+        let or = vec![or].pop().unwrap();
+        assert_eq!(&*or, &[1, 2, 3]);
+        assert_eq!(Rc::strong_count(&owner), 2);
+
+        let or: BoxRef<(), [u8]> = BoxRef::new(Box::new(())).map::<_, [u8]>(|_| &[1, 2, 3]);
+        assert_eq!(or.len(), 3);
+        assert_eq!(*or.owner(), Box::new(()));
     }
 
     #[test]
-    fn into() {
-        let or: OwningRef<Box<()>, ()> = Box::new(()).into();
-        assert_eq!(&*or, &());
+    fn ownership() {
+        use super::{ArcOwnership, BoxOwnership, Ownership};
+
+        struct Container<Own: Ownership> {
+            names: Vec<OwningRef<Own::Owner<String>, str>>,
+        }
+
+        impl<Own: Ownership> Container<Own> {
+            fn new() -> Self {
+                Container { names: Vec::new() }
+            }
+
+            fn push_trimmed(&mut self, name: &str) {
+                let or = Own::new_ref(name.to_string()).map(|s| s.trim());
+                self.names.push(or);
+            }
+
+            fn joined(&self) -> String {
+                self.names.iter().map(|n| &**n).collect::<Vec<_>>().join(",")
+            }
+        }
+
+        let mut boxed = Container::<BoxOwnership>::new();
+        boxed.push_trimmed(" a ");
+        boxed.push_trimmed("b  ");
+        assert_eq!(boxed.joined(), "a,b");
+
+        let mut shared = Container::<ArcOwnership>::new();
+        shared.push_trimmed("  c");
+        let clone = shared.names[0].clone();
+        assert_eq!(&*clone, "c");
+        assert_eq!(clone.owner_strong_count(), 2);
     }
 
     #[test]
-    fn map_offset_ref() {
-        let or: BoxRef<Example> = Box::new(example()).into();
-        let or: BoxRef<_, u32> = or.map(|x| &x.0);
-        assert_eq!(&*or, &42);
+    fn erase_sync_owner() {
+        use std::thread;
+        use super::SyncErased;
 
-        let or: BoxRef<Example> = Box::new(example()).into();
-        let or: BoxRef<_, u8> = or.map(|x| &x.2[1]);
-        assert_eq!(&*or, &2);
+        let refs: Vec<OwningRef<Box<SyncErased>, [i32]>> = vec![
+            BoxRef::new(Box::new([1, 2, 3])).map(|a| &a[..]).erase_sync_owner(),
+            BoxRef::new(Box::new(vec![4, 5])).map(|v| &v[..]).erase_sync_owner(),
+            BoxRef::new(Box::new((0, [6]))).map(|t| &t.1[..]).erase_sync_owner(),
+        ];
+
+        let sum = thread::spawn(move || {
+            refs.iter().map(|r| r.iter().sum::<i32>()).sum::<i32>()
+        }).join().unwrap();
+        assert_eq!(sum, 21);
     }
 
     #[test]
-    fn map_heap_ref() {
-        let or: BoxRef<Example> = Box::new(example()).into();
-        let or: BoxRef<_, str> = or.map(|x| &x.1[..5]);
-        assert_eq!(&*or, "hello");
+    fn flatten_all() {
+        use std::sync::Arc;
+
+        let data = Arc::new(example());
+
+        let first = ArcRef::new(data.clone()).map(|e| &e.2);
+        let second = OwningRef::new(first).map(|a| &a[1..]);
+        let flat: ArcRef<Example, [u8]> = second.flatten_all();
+        assert_eq!(&*flat, &[2, 3]);
+        assert_eq!(Arc::strong_count(&data), 2);
+
+        let first = ArcRef::new(data.clone()).map(|e| &e.1[..]);
+        let second = OwningRef::new(first).map(|s| &s[6..]);
+        let third = OwningRef::new(second.clone()).map(|s| &s[..3]);
+        assert_eq!(Arc::strong_count(&data), 4);
+
+        let flat: ArcRef<Example, str> = third.flatten_all();
+        assert_eq!(&*flat, "wor");
+        assert_eq!(Arc::strong_count(&data), 4);
+        drop(second);
+        assert_eq!(Arc::strong_count(&data), 3);
+        assert!(::std::ptr::eq(&*flat, &data.1[6..9]));
     }
 
     #[test]
-    fn map_static_ref() {
-        let or: BoxRef<()> = Box::new(()).into();
-        let or: BoxRef<_, str> = or.map(|_| "hello");
+    fn map_with_token() {
+        let or = BoxRef::new(Box::new(example()));
+        let (or, len) = or.map_with_token(|e| {
+            let sub = &e.1[..5];
+            (sub, sub.len())
+        });
         assert_eq!(&*or, "hello");
+        assert_eq!(len, 5);
+
+        let (or, upper) = or.map_with_token(|s| (&s[1..], s.to_uppercase()));
+        assert_eq!(&*or, "ello");
+        assert_eq!(upper, "HELLO");
     }
 
+    #[cfg(feature = "nightly")]
     #[test]
-    fn map_chained() {
-        let or: BoxRef<String> = Box::new(example().1).into();
-        let or: BoxRef<_, str> = or.map(|x| &x[1..5]);
-        let or: BoxRef<_, str> = or.map(|x| &x[..2]);
-        assert_eq!(&*or, "el");
+    fn cow_set() {
+        use std::sync::Arc;
+
+        let or: ArcRef<[i32]> = super::arc_ref_from_vec(vec![1, 2, 3, 4]);
+        let tail = or.clone().map(|s| &s[2..]);
+
+        // shared: the slice is cloned, and the projection kept
+        let edited = tail.clone().cow_set(1, 40);
+        assert_eq!(&*edited, &[3, 40]);
+        assert_eq!(&**edited.owner(), &[1, 2, 3, 40]);
+        assert_eq!(&*tail, &[3, 4]);
+        assert_eq!(&*or, &[1, 2, 3, 4]);
+
+        // unique: the slice is edited in place
+        let ptr = edited.owner_ptr();
+        let edited = edited.cow_set(0, 30);
+        assert_eq!(&*edited, &[30, 40]);
+        assert_eq!(edited.owner_ptr(), ptr);
+        assert_eq!(Arc::strong_count(edited.owner()), 1);
+
+        // not part of the owner: only the referenced slice is cloned
+        let unrelated = or.map(|_| &[7, 8][..]).cow_set(1, 9);
+        assert_eq!(&**unrelated.owner(), &[7, 9]);
     }
 
     #[test]
-    fn map_chained_inference() {
-        let or = BoxRef::new(Box::new(example().1))
-            .map(|x| &x[..5])
-            .map(|x| &x[1..3]);
-        assert_eq!(&*or, "el");
+    fn debug_truncated() {
+        let long = "abc".repeat(100);
+        let or = super::StringRef::new(long);
+        assert_eq!(format!("{:?}", or.debug_truncated(4)), "\"abca\"...");
+        assert_eq!(format!("{:?}", or.debug_truncated(300)), format!("{:?}", &*or));
+
+        let or = super::StringRef::new("äöü".to_string());
+        assert_eq!(format!("{:?}", or.debug_truncated(2)), "\"äö\"...");
+
+        let or = VecRef::new((0..100).collect::<Vec<i32>>());
+        assert_eq!(format!("{:?}", or.debug_truncated(3)), "[0, 1, 2, ..]");
+        assert_eq!(format!("{:?}", or.debug_truncated(100)), format!("{:?}", &*or));
     }
 
     #[test]
-    fn owner() {
-        let or: BoxRef<String> = Box::new(example().1).into();
-        let or = or.map(|x| &x[..5]);
-        assert_eq!(&*or, "hello");
-        assert_eq!(&**or.owner(), "hello world");
+    fn get_mut_owner() {
+        use std::sync::Arc;
+
+        let mut or: ArcRef<Vec<i32>> = ArcRef::new(Arc::new(vec![1, 2, 3]));
+        or.get_mut_owner().unwrap().push(4);
+        assert_eq!(*or, [1, 2, 3, 4]);
+
+        // a reprojected reference is reset to the whole target
+        let mut or: ArcRef<Vec<i32>> = or.map(|_| {
+            static OTHER: Vec<i32> = Vec::new();
+            &OTHER
+        });
+        assert!(or.is_empty());
+        or.get_mut_owner().unwrap().clear();
+        assert!(::std::ptr::eq(&*or, &**or.owner()));
+
+        let other = or.clone();
+        assert!(or.get_mut_owner().is_none());
+        drop(other);
+        assert!(or.get_mut_owner().is_some());
     }
 
+    #[cfg(feature = "nightly")]
     #[test]
-    fn into_inner() {
-        let or: BoxRef<String> = Box::new(example().1).into();
-        let or = or.map(|x| &x[..5]);
-        assert_eq!(&*or, "hello");
-        let s = *or.into_inner();
-        assert_eq!(&s, "hello world");
+    fn arc_str_ref() {
+        use std::thread;
+
+        let or: ArcRef<str> = super::arc_str_ref("hello world");
+        let word = or.clone().map(|s| &s[..5]);
+        assert_eq!(&*word, "hello");
+
+        let sent = word.clone();
+        let len = thread::spawn(move || sent.len()).join().unwrap();
+        assert_eq!(len, 5);
+        assert_eq!(word.owner_strong_count(), 2);
     }
 
     #[test]
-    fn fmt_debug() {
-        let or: BoxRef<String> = Box::new(example().1).into();
-        let or = or.map(|x| &x[..5]);
-        let s = format!("{:?}", or);
-        assert_eq!(&s, "OwningRef { owner: \"hello world\", reference: \"hello\" }");
+    fn map_into_vec() {
+        use std::rc::Rc;
+
+        let data = Rc::new(example());
+        let or = RcRef::new(data.clone()).map(|e| &e.2);
+        let or = or.map_into_vec(|a, out| {
+            for x in a.iter() {
+                out.push(u32::from(*x) * 100);
+            }
+        });
+
+        assert_eq!(&*or, &[100, 200, 300]);
+        assert_eq!(Rc::strong_count(&data), 2);
+        let moved = vec![or];
+        assert_eq!(moved[0][2], 300);
+        drop(moved);
+        assert_eq!(Rc::strong_count(&data), 1);
     }
 
     #[test]
-    fn erased_owner() {
-        let o1: BoxRef<Example, str> = BoxRef::new(Box::new(example()))
-            .map(|x| &x.1[..]);
+    fn ref_owning_ref() {
+        use super::RefOwningRef;
 
-        let o2: BoxRef<String, str> = BoxRef::new(Box::new(example().1))
-            .map(|x| &x[..]);
+        let local = example();
+        let or: RefOwningRef<Example, str> = RefOwningRef::from_ref(&local).map(|e| &e.1[..]);
+        assert_eq!(&*or, "hello world");
+        assert!(::std::ptr::eq(*or.owner(), &local));
+    }
 
-        let os: Vec<ErasedBoxRef<str>> = vec![o1.erase_owner(), o2.erase_owner()];
-        assert!(os.iter().all(|e| &e[..] == "hello world"));
+    #[test]
+    fn map_dyn() {
+        use std::fmt::Display;
+
+        let or: BoxRef<Example> = BoxRef::new(Box::new(example()));
+        let or = or.map_dyn::<dyn Display, _>(|e| &e.0);
+        assert_eq!(format!("{}", &*or), "42");
+        assert_eq!(or.owner().1, "hello world");
     }
 
     #[test]
-    fn non_static_erased_owner() {
-        let foo = [413, 612];
-        let bar = &foo;
+    fn project_pin() {
+        use std::marker::PhantomPinned;
+        use std::pin::Pin;
 
-        let o: BoxRef<&[i32; 2]> = Box::new(bar).into();
-        let o: BoxRef<&[i32; 2], i32> = o.map(|a: &&[i32; 2]| &a[0]);
-        let o: BoxRef<Erased, i32> = o.erase_owner();
+        struct Pinned(Example, PhantomPinned);
 
-        assert_eq!(*o, 413);
+        let boxed = Box::pin(Pinned(example(), PhantomPinned));
+        let field: *const [u8; 3] = &boxed.0 .2;
+        let mut or = OwningRefMut::project_pin(boxed, |p| unsafe {
+            p.map_unchecked_mut(|p| &mut p.0 .2)
+        });
+        or.as_mut()[1] = 20;
+
+        let moved = [or];
+        let pinned: Pin<&[u8; 3]> = moved[0].as_ref();
+        assert_eq!(*pinned, [1, 20, 3]);
+        assert!(::std::ptr::eq(&*pinned, field));
+    }
+
+    #[test]
+    fn map_lazy() {
+        use std::cell::{Cell, OnceCell};
+
+        let runs = Cell::new(0);
+        let or = BoxRef::new(Box::new(example())).map(|e| &e.1);
+        let or = or.map_lazy(OnceCell::new, |s| {
+            runs.set(runs.get() + 1);
+            s.split(' ').count()
+        });
+        assert_eq!(*or, 2);
+        assert_eq!(*or, 2);
+        assert_eq!(runs.get(), 1);
+        assert_eq!(or.owner().0 .1, "hello world");
+
+        let or = BoxRef::new(Box::new(example())).map_lazy(|| OnceCell::from(7), |e| {
+            runs.set(runs.get() + 1);
+            e.0
+        });
+        assert_eq!(*or, 7);
+        assert_eq!(runs.get(), 1);
+    }
+
+    #[test]
+    fn shared_or_owned() {
+        use std::sync::Arc;
+        use super::SharedOrOwned;
+
+        let value = ("hello world".to_string(), [1, 2, 3]);
+        let shared = Arc::new(value.clone());
+        let or = OwningRef::new(SharedOrOwned::Shared(shared.clone())).map(|v| &v.0[..]);
+        let copy = or.clone_adaptive();
+        assert_eq!(&*copy, "hello world");
+        assert!(::std::ptr::eq(&*or, &*copy));
+        assert_eq!(Arc::strong_count(&shared), 3);
+
+        let or = OwningRef::new(SharedOrOwned::Owned(Box::new(value))).map(|v| &v.1);
+        let copy = or.clone_adaptive();
+        assert_eq!(*copy, [1, 2, 3]);
+        assert!(!::std::ptr::eq(&*or, &*copy));
+        assert!(!::std::ptr::eq(&**or.owner(), &**copy.owner()));
+        assert!(::std::ptr::eq(&*copy, &copy.owner().1));
+        drop(or);
+        assert_eq!(*copy, [1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "referent does not lie within the owned value")]
+    fn shared_or_owned_outside_projection() {
+        use super::SharedOrOwned;
+
+        let value = ("hello world".to_string(), [1, 2, 3]);
+        let or = OwningRef::new(SharedOrOwned::Owned(Box::new(value))).map(|v| &v.0[..]);
+        let _ = or.clone_adaptive();
+    }
+
+    #[test]
+    #[should_panic(expected = "referent does not lie within the owned value")]
+    fn shared_or_owned_static_projection() {
+        use super::SharedOrOwned;
+
+        let or = OwningRef::new(SharedOrOwned::Owned(Box::new(0))).map(|_| &[1, 2, 3][..]);
+        let _ = or.clone_adaptive();
+    }
+
+    #[test]
+    fn leak() {
+        let or: BoxRef<Example, str> = BoxRef::new(Box::new(example())).map(|e| &e.1[..5]);
+        let leaked = or.leak();
+        assert_eq!(&*leaked, "hello");
+
+        let s: &'static str = leaked.into_inner();
+        let handle = ::std::thread::spawn(move || s.len());
+        assert_eq!(handle.join().unwrap(), 5);
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn cow_slice_to_arc() {
+        use super::CowRef;
+        use std::borrow::Cow;
+
+        static DATA: [u32; 4] = [1, 2, 3, 4];
+        let borrowed: CowRef<'static, [u32]> = CowRef::new(Cow::Borrowed(&DATA[..]));
+        let owned: CowRef<'static, [u32]> = CowRef::new(Cow::Owned(DATA.to_vec()));
+
+        let borrowed: ArcRef<[u32]> = borrowed.map(|s| &s[1..3]).cow_slice_to_arc();
+        let owned: ArcRef<[u32]> = owned.map(|s| &s[1..3]).cow_slice_to_arc();
+
+        assert_eq!(&*borrowed, &[2, 3]);
+        assert_eq!(&*borrowed, &*owned);
+        assert_eq!(&**borrowed.owner(), &**owned.owner());
+        assert!(!::std::ptr::eq(&*borrowed, &DATA[1..3]));
+        assert_eq!(*borrowed.clone().map(|s| &s[0]), 2);
+    }
+
+    #[test]
+    fn new_checked() {
+        use super::StableAddress;
+        use std::ops::Deref;
+
+        struct Stable(Box<[u32; 3]>);
+        impl Deref for Stable {
+            type Target = [u32; 3];
+            fn deref(&self) -> &[u32; 3] { &self.0 }
+        }
+        unsafe impl StableAddress for Stable {}
+
+        let or = OwningRef::new_checked(Stable(Box::new([1, 2, 3]))).map(|a| &a[1]);
+        assert_eq!(*or, 2);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "the target of the owner moved along with it")]
+    fn new_checked_unstable() {
+        use super::StableAddress;
+        use std::ops::Deref;
+
+        struct Inline([u32; 3]);
+        impl Deref for Inline {
+            type Target = [u32; 3];
+            fn deref(&self) -> &[u32; 3] { &self.0 }
+        }
+        unsafe impl StableAddress for Inline {}
+
+        let _ = OwningRef::new_checked(Inline([1, 2, 3]));
+    }
+
+    #[test]
+    fn map_with_index() {
+        use std::collections::HashMap;
+
+        let or = BoxRef::new(Box::new(example())).map(|e| &e.1[..]);
+        let or = or.map_with_index(
+            |s| {
+                let mut index = HashMap::new();
+                let mut offset = 0;
+                for word in s.split(' ') {
+                    index.insert(word.to_string(), offset);
+                    offset += word.len() + 1;
+                }
+                index
+            },
+            |s, index| {
+                let start = index["world"];
+                &s[start..start + "world".len()]
+            },
+        );
+
+        assert_eq!(&*or, "world");
+        assert_eq!(or.owner().1["hello"], 0);
+        assert_eq!(or.owner().0 .0, 42);
+    }
+
+    #[test]
+    fn apply_composed() {
+        use super::{Compose, Projection};
+        use std::ops::Range;
+
+        struct Bytes;
+        impl Projection<Example> for Bytes {
+            type Output = [u8];
+            fn project<'a>(&self, e: &'a Example) -> &'a [u8] { &e.2 }
+        }
+
+        struct Sub(Range<usize>);
+        impl Projection<[u8]> for Sub {
+            type Output = [u8];
+            fn project<'a>(&self, s: &'a [u8]) -> &'a [u8] { &s[self.0.clone()] }
+        }
+
+        let projection = Compose::new(Bytes, Sub(1..3));
+        let or: BoxRef<Example, [u8]> = BoxRef::new(Box::new(example())).apply(&projection);
+        assert_eq!(&*or, &[2, 3]);
+
+        let mut other = example();
+        other.2 = [7, 8, 9];
+        let other: BoxRef<Example, [u8]> = BoxRef::new(Box::new(other)).apply(&projection);
+        assert_eq!(&*other, &[8, 9]);
+        assert_eq!(&*or, &[2, 3]);
+
+        let or = or.apply(Compose::new(Sub(1..2), Sub(0..1)));
+        assert_eq!(&*or, &[3]);
+    }
+
+    #[test]
+    fn map_with_guard() {
+        use std::sync::{Arc, Mutex};
+
+        struct Noisy(Arc<Mutex<Vec<&'static str>>>, Example);
+        impl Drop for Noisy {
+            fn drop(&mut self) {
+                self.0.lock().unwrap().push("owner");
+            }
+        }
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let or = BoxRef::new(Box::new(Noisy(log.clone(), example())));
+        let (or, guard) = or.map_with_guard(|n| &n.1 .2);
+
+        let events = log.clone();
+        guard.register(move || events.lock().unwrap().push("callback"));
+        drop(guard);
+        assert_eq!(*or, [1, 2, 3]);
+        assert!(log.lock().unwrap().is_empty());
+
+        drop(or);
+        assert_eq!(*log.lock().unwrap(), ["owner", "callback"]);
+    }
+
+    #[test]
+    fn project_owned_trait_objects() {
+        use std::fmt::Display;
+
+        let items: Box<[Box<dyn Display>]> = vec![
+            Box::new(42) as Box<dyn Display>,
+            Box::new("hello"),
+            Box::new(1.5),
+        ].into_boxed_slice();
+        let or: BoxRef<[Box<dyn Display>]> = BoxRef::new(items);
+        let or: BoxRef<[Box<dyn Display>], Box<dyn Display>> = or.project_owned(1);
+
+        assert_eq!(or.to_string(), "hello");
+        let or = or.map(|d| &**d);
+        assert_eq!(format!("<{}>", &*or), "<hello>");
+    }
+
+    #[test]
+    fn reborrow_shared() {
+        let mut or: OwningRefMut<Box<Example>, String> =
+            OwningRefMut::new(Box::new(example())).map_mut(|e| &mut e.1);
+
+        {
+            let shared = or.reborrow_shared();
+            let word = shared.map(|s| &s[6..]);
+            assert_eq!(&*word, "world");
+            assert_eq!(word.owner().0, 42);
+        }
+
+        or.push('!');
+        assert_eq!(&*or.reborrow_shared(), "hello world!");
+    }
+
+    #[test]
+    fn hash_set_get_owned() {
+        use std::collections::HashSet;
+        use std::sync::Arc;
+
+        let set: HashSet<String> = ["hello", "world"].iter().map(|s| s.to_string()).collect();
+        let set = Arc::new(set);
+        let or: ArcRef<HashSet<String>> = ArcRef::new(set.clone());
+
+        let world: ArcRef<HashSet<String>, String> = or.get_owned("world").unwrap();
+        assert_eq!(&*world, "world");
+        assert!(::std::ptr::eq(&*world, set.get("world").unwrap()));
+        assert!(or.get_owned("moon").is_none());
+
+        drop(or);
+        drop(set);
+        assert_eq!(world.len(), 5);
+    }
+
+    #[test]
+    fn erase_downcast_round_trip() {
+        use std::any::Any;
+
+        let or: BoxRef<Example, u32> = BoxRef::new(Box::new(example())).map(|e| &e.0);
+        let reference: *const u32 = &*or;
+        let owner = or.owner_ptr();
+
+        let erased: OwningRef<Box<dyn Any>, u32> = or.erase_owner_any();
+        let mut stored = vec![erased];
+        assert_eq!(*stored[0], 42);
+
+        let erased = stored.pop().unwrap();
+        let erased = erased.downcast_owner::<String>().unwrap_err();
+        let or: BoxRef<Example, u32> = erased.downcast_owner().unwrap();
+        assert!(::std::ptr::eq(&*or, reference));
+        assert!(::std::ptr::eq(or.owner_ptr(), owner));
+        assert_eq!(*or, 42);
+
+        let or = OwningRef::new(or.into_inner()).map(|e| &e.2);
+        let or = or.erase_owner_any().downcast_owner::<Example>().unwrap().map(|a| &a[1..]);
+        assert_eq!(&*or, &[2, 3]);
+        assert!(::std::ptr::eq(or.owner_ptr(), owner));
+    }
+}
+
+#[cfg(all(test, owning_ref_bench))]
+mod benches {
+    extern crate test;
+
+    use self::test::{Bencher, black_box};
+    use std::ptr;
+    use super::{VecRef, SlicedOwningRef};
+
+    fn slice_ref() -> VecRef<u32, [u32]> {
+        VecRef::new((0..1024).collect()).map(|v| &v[1..])
+    }
+
+    #[bench]
+    fn deref_sized(b: &mut Bencher) {
+        let or = VecRef::new(vec![1u32, 2, 3]).map(|v| &v[1]);
+        b.iter(|| {
+            let or = black_box(&or);
+            (0..1000).map(|_| black_box(**or)).sum::<u32>()
+        });
+    }
+
+    #[bench]
+    fn deref_plain_ref(b: &mut Bencher) {
+        let v = Box::new([1u32, 2, 3]);
+        let r = &v[1];
+        b.iter(|| {
+            let r = black_box(&r);
+            (0..1000).map(|_| black_box(**r)).sum::<u32>()
+        });
+    }
+
+    #[bench]
+    fn deref_len(b: &mut Bencher) {
+        let or = slice_ref();
+        b.iter(|| {
+            let or = black_box(&or);
+            (0..1000).map(|_| black_box(or.len())).sum::<usize>()
+        });
+    }
+
+    #[bench]
+    fn cached_len(b: &mut Bencher) {
+        let or = SlicedOwningRef::new(slice_ref());
+        b.iter(|| {
+            let or = black_box(&or);
+            (0..1000).map(|_| black_box(or.len())).sum::<usize>()
+        });
+    }
+
+    #[cfg(feature = "nightly")]
+    #[bench]
+    fn arc_from_iter(b: &mut Bencher) {
+        b.iter(|| super::arc_ref_from_iter(black_box(0..1024u32)).map(|s| &s[1..]));
+    }
+
+    #[cfg(feature = "nightly")]
+    #[bench]
+    fn arc_from_vec(b: &mut Bencher) {
+        b.iter(|| super::arc_ref_from_vec(black_box(0..1024u32).collect()).map(|s| &s[1..]));
+    }
+
+    #[bench]
+    fn owner_ptr(b: &mut Bencher) {
+        let or = slice_ref();
+        let other: *const [u32] = &**or.owner();
+        b.iter(|| {
+            let or = black_box(&or);
+            (0..1000).filter(|_| ptr::eq(black_box(or.owner_ptr()), other)).count()
+        });
     }
 }